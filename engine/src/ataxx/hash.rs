@@ -0,0 +1,68 @@
+use super::{
+    position::{Position, Side},
+    symmetry::SYMMETRIES,
+};
+
+// splitmix64 finaliser
+const fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+impl Position {
+    // Hash of the board and side to move, the move counters are left out
+    pub fn hash(&self) -> u64 {
+        let mut h = mix(self.black.0 ^ 0x9e3779b97f4a7c15);
+        h = mix(h ^ self.white.0);
+        h = mix(h ^ self.gaps.0);
+
+        match self.turn {
+            Side::Black => h,
+            Side::White => !h,
+        }
+    }
+
+    // Symmetry that takes the position to its canonical form, the one with
+    // the lowest hash. Symmetric positions share their canonical form.
+    pub fn canonical_symmetry(&self) -> usize {
+        (0..SYMMETRIES)
+            .min_by_key(|&sym| self.transform(sym).hash())
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hash_differs() {
+        let black = Position::from_fen("x5o/7/7/7/7/7/o5x x 0 1").unwrap();
+        let white = Position::from_fen("x5o/7/7/7/7/7/o5x o 0 1").unwrap();
+        let other = Position::from_fen("x5o/7/7/7/7/7/o4xx x 0 1").unwrap();
+        let gaps = Position::from_fen("x5o/7/3-3/7/7/7/o5x x 0 1").unwrap();
+
+        assert_ne!(black.hash(), white.hash());
+        assert_ne!(black.hash(), other.hash());
+        assert_ne!(black.hash(), gaps.hash());
+    }
+
+    #[test]
+    fn hash_ignores_counters() {
+        let a = Position::from_fen("x5o/7/7/7/7/7/o5x x 0 1").unwrap();
+        let b = Position::from_fen("x5o/7/7/7/7/7/o5x x 10 6").unwrap();
+        assert_eq!(a.hash(), b.hash());
+    }
+
+    #[test]
+    fn canonical_hash() {
+        let a = Position::from_fen("x5o/7/7/7/7/7/o4xx o 0 1").unwrap();
+        let b = Position::from_fen("xx4o/7/7/7/7/7/o5x o 0 1").unwrap();
+        assert_ne!(a.hash(), b.hash());
+        assert_eq!(
+            a.transform(a.canonical_symmetry()),
+            b.transform(b.canonical_symmetry())
+        );
+    }
+}
@@ -1,3 +1,6 @@
 pub mod bitboard;
 pub mod fen;
+pub mod hash;
 pub mod position;
+pub mod startpos;
+pub mod symmetry;
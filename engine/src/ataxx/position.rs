@@ -7,7 +7,7 @@ pub enum Side {
     White,
 }
 
-#[derive(PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Outcome {
     BlackWin,
     WhiteWin,
@@ -66,7 +66,7 @@ impl Position {
         }
     }
 
-    fn both_sides(&self) -> BitBoard {
+    pub fn both_sides(&self) -> BitBoard {
         self.black | self.white
     }
 
@@ -0,0 +1,76 @@
+// Named start positions, so openings can be picked without pasting fens.
+// Every layout has the four corner stones and gaps that look the same to
+// both sides.
+
+pub struct StartPos {
+    pub name: &'static str,
+    pub fen: &'static str,
+}
+
+pub const STANDARD: &str = "standard";
+
+pub const CATALOGUE: [StartPos; 7] = [
+    StartPos {
+        name: STANDARD,
+        fen: "x5o/7/7/7/7/7/o5x x 0 1",
+    },
+    StartPos {
+        name: "fourhole",
+        fen: "x5o/7/2-1-2/7/2-1-2/7/o5x x 0 1",
+    },
+    StartPos {
+        name: "centre",
+        fen: "x5o/7/7/3-3/7/7/o5x x 0 1",
+    },
+    StartPos {
+        name: "cross",
+        fen: "x2-2o/3-3/3-3/---1---/3-3/3-3/o2-2x x 0 1",
+    },
+    StartPos {
+        name: "edges",
+        fen: "x2-2o/7/7/-5-/7/7/o2-2x x 0 1",
+    },
+    StartPos {
+        name: "ring",
+        fen: "x5o/7/2---2/2-1-2/2---2/7/o5x x 0 1",
+    },
+    StartPos {
+        name: "diamond",
+        fen: "x5o/3-3/2-1-2/1-3-1/2-1-2/3-3/o5x x 0 1",
+    },
+];
+
+// Names are matched case-insensitively
+pub fn find(name: &str) -> Option<&'static StartPos> {
+    CATALOGUE.iter().find(|s| s.name.eq_ignore_ascii_case(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ataxx::{position::Position, symmetry::SYMMETRIES};
+
+    #[test]
+    fn catalogue_layouts() {
+        assert_eq!(
+            Position::from_fen(find(STANDARD).unwrap().fen),
+            Ok(Position::default())
+        );
+
+        for (i, start) in CATALOGUE.iter().enumerate() {
+            let pos = Position::from_fen(start.fen).unwrap();
+            assert_eq!(pos.both_sides(), Position::default().both_sides());
+            assert!(CATALOGUE[..i].iter().all(|s| s.name != start.name));
+
+            for sym in 0..SYMMETRIES {
+                assert_eq!(pos.gaps.transform(sym), pos.gaps, "{}", start.name);
+            }
+        }
+    }
+
+    #[test]
+    fn find_by_name() {
+        assert_eq!(find("Cross").unwrap().name, "cross");
+        assert!(find("hexagon").is_none());
+    }
+}
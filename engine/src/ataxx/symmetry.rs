@@ -0,0 +1,121 @@
+use super::{bitboard::BitBoard, position::Position};
+
+// The 8 symmetries of the square board: identity, mirrors, rotations
+// and the two diagonal flips
+pub const SYMMETRIES: usize = 8;
+
+pub fn transform_square(sq: u8, sym: usize) -> u8 {
+    let (file, rank) = (sq % 7, sq / 7);
+    let (file, rank) = match sym {
+        0 => (file, rank),
+        1 => (6 - file, rank),
+        2 => (file, 6 - rank),
+        3 => (6 - file, 6 - rank),
+        4 => (rank, file),
+        5 => (6 - rank, file),
+        6 => (rank, 6 - file),
+        7 => (6 - rank, 6 - file),
+        _ => unreachable!(),
+    };
+
+    rank * 7 + file
+}
+
+// The symmetry that undoes the given one
+pub fn inverse(sym: usize) -> usize {
+    match sym {
+        5 => 6,
+        6 => 5,
+        sym => sym,
+    }
+}
+
+// The symmetry that applies first and then second. Every symmetry sends
+// b1 somewhere else, so that square is enough to tell them apart.
+pub fn compose(first: usize, second: usize) -> usize {
+    let target = transform_square(transform_square(1, first), second);
+    (0..SYMMETRIES)
+        .find(|&sym| transform_square(1, sym) == target)
+        .unwrap()
+}
+
+impl BitBoard {
+    pub fn transform(&self, sym: usize) -> BitBoard {
+        let mut bb = BitBoard(0);
+        for sq in *self {
+            bb |= BitBoard::from_index(transform_square(sq, sym));
+        }
+
+        bb
+    }
+}
+
+impl Position {
+    pub fn transform(&self, sym: usize) -> Position {
+        Position {
+            black: self.black.transform(sym),
+            white: self.white.transform(sym),
+            gaps: self.gaps.transform(sym),
+            ..*self
+        }
+    }
+
+    #[cfg(test)]
+    pub fn is_symmetric(&self, sym: usize) -> bool {
+        let other = self.transform(sym);
+        other.black == self.black && other.white == self.white && other.gaps == self.gaps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transform_square_is_a_permutation() {
+        for sym in 0..SYMMETRIES {
+            let mut seen = BitBoard(0);
+            for sq in 0..49 {
+                seen |= BitBoard::from_index(transform_square(sq, sym));
+            }
+            assert_eq!(seen, BitBoard::full());
+        }
+    }
+
+    #[test]
+    fn inverse_and_compose() {
+        let pos = Position::from_fen("x5o/7/2-1-2/7/1-5/7/o4xx x 0 1").unwrap();
+        for a in 0..SYMMETRIES {
+            assert_eq!(pos.transform(a).transform(inverse(a)), pos);
+            for b in 0..SYMMETRIES {
+                assert_eq!(pos.transform(a).transform(b), pos.transform(compose(a, b)));
+            }
+        }
+    }
+
+    #[test]
+    fn transform_corners() {
+        // a1 is square 0, g1 is 6, a7 is 42
+        assert_eq!(transform_square(0, 1), 6);
+        assert_eq!(transform_square(0, 2), 42);
+        assert_eq!(transform_square(0, 3), 48);
+        assert_eq!(transform_square(6, 4), 42);
+    }
+
+    #[test]
+    fn startpos_is_symmetric() {
+        let pos = Position::default();
+        for sym in [0, 3, 4, 7] {
+            assert!(pos.is_symmetric(sym));
+        }
+        assert!(!pos.is_symmetric(1));
+    }
+
+    #[test]
+    fn perft_is_invariant() {
+        let pos = Position::from_fen("x5o/7/2-1-2/7/1-5/7/o4xx x 0 1").unwrap();
+        for sym in 0..SYMMETRIES {
+            assert_eq!(pos.transform(sym).perft(3), pos.perft(3));
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+// f32 stored as its bit pattern, std has no atomic floats
+#[derive(Debug, Default)]
+pub struct AtomicF32(AtomicU32);
+
+impl AtomicF32 {
+    pub fn new(value: f32) -> Self {
+        AtomicF32(AtomicU32::new(value.to_bits()))
+    }
+
+    pub fn load(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    pub fn fetch_add(&self, delta: f32) -> f32 {
+        let mut current = self.0.load(Ordering::Relaxed);
+        loop {
+            let new = (f32::from_bits(current) + delta).to_bits();
+            match self
+                .0
+                .compare_exchange_weak(current, new, Ordering::Relaxed, Ordering::Relaxed)
+            {
+                Ok(old) => return f32::from_bits(old),
+                Err(actual) => current = actual,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fetch_add() {
+        let a = AtomicF32::new(0.5);
+        assert_eq!(a.fetch_add(0.25), 0.5);
+        assert_eq!(a.load(), 0.75);
+
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    for _ in 0..1000 {
+                        a.fetch_add(1.0);
+                    }
+                });
+            }
+        });
+        assert_eq!(a.load(), 4000.75);
+    }
+}
@@ -0,0 +1,207 @@
+// Opening book, one line per position and move:
+//
+//     <position hash in hex> <move> <weight> <wins> <draws> <losses>
+//
+// Results are counted for the side to move. Empty lines and lines starting
+// with # are skipped.
+
+use super::moves::Move;
+use crate::ataxx::position::Position;
+use fastrand::Rng;
+use std::collections::HashMap;
+
+#[derive(Debug, PartialEq)]
+pub enum BookError {
+    Io(String),
+    // Line number, counted from 1
+    Line(usize),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BookMove {
+    pub mv: Move,
+    // Moves with weight 0 are kept for their results but never played
+    pub weight: u32,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Book {
+    entries: HashMap<u64, Vec<BookMove>>,
+}
+
+impl BookMove {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+}
+
+impl Book {
+    pub fn load(path: &str) -> Result<Book, BookError> {
+        let text = std::fs::read_to_string(path).map_err(|e| BookError::Io(e.to_string()))?;
+        Book::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Book, BookError> {
+        let mut book = Book::default();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let entry = (|| {
+                let [hash, mv, weight, wins, draws, losses] = fields.as_slice() else {
+                    return None;
+                };
+
+                let hash = u64::from_str_radix(hash, 16).ok()?;
+                let book_move = BookMove {
+                    mv: mv.parse().ok()?,
+                    weight: weight.parse().ok()?,
+                    wins: wins.parse().ok()?,
+                    draws: draws.parse().ok()?,
+                    losses: losses.parse().ok()?,
+                };
+                Some((hash, book_move))
+            })();
+
+            let (hash, book_move) = entry.ok_or(BookError::Line(i + 1))?;
+            book.entries.entry(hash).or_default().push(book_move);
+        }
+
+        Ok(book)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn add(&mut self, hash: u64, book_move: BookMove) {
+        self.entries.entry(hash).or_default().push(book_move);
+    }
+
+    // Positions sorted by hash so the same book always gives the same file
+    pub fn write(&self, writer: &mut impl std::io::Write) -> std::io::Result<()> {
+        let mut hashes: Vec<&u64> = self.entries.keys().collect();
+        hashes.sort_unstable();
+
+        for hash in hashes {
+            for b in self.entries[hash].iter() {
+                writeln!(
+                    writer,
+                    "{:x} {} {} {} {} {}",
+                    hash, b.mv, b.weight, b.wins, b.draws, b.losses
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Weighted random choice among the legal book moves of the position
+    pub fn probe(&self, position: &Position, rng: &mut Rng) -> Option<BookMove> {
+        let moves = position.generate_moves();
+        let candidates: Vec<&BookMove> = self
+            .entries
+            .get(&position.hash())?
+            .iter()
+            .filter(|b| b.weight > 0 && moves.as_slice().contains(&b.mv))
+            .collect();
+
+        let total: u64 = candidates.iter().map(|b| b.weight as u64).sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut target = rng.u64(..total);
+        for book_move in candidates {
+            if target < book_move.weight as u64 {
+                return Some(*book_move);
+            }
+            target -= book_move.weight as u64;
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn startpos_book() -> Book {
+        let hash = Position::default().hash();
+        Book::parse(&format!(
+            "# startpos\n\
+             {hash:x} f2 3 10 2 5\n\
+             {hash:x} b6 1 4 0 4\n\
+             {hash:x} g1g3 0 1 0 9\n\
+             {hash:x} d4 5 0 0 0\n"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn parse() {
+        let book = startpos_book();
+        assert_eq!(book.len(), 1);
+
+        let moves = &book.entries[&Position::default().hash()];
+        assert_eq!(moves.len(), 4);
+        assert_eq!(moves[0].mv, Move::new(12, 12));
+        assert_eq!(moves[0].games(), 17);
+        assert_eq!(moves[2].mv, Move::new(6, 20));
+
+        assert_eq!(Book::parse("12ab f2 1 0 0"), Err(BookError::Line(1)));
+        assert_eq!(Book::parse("\nxyz f2 1 0 0 0"), Err(BookError::Line(2)));
+        assert_eq!(Book::parse("12ab h9 1 0 0 0"), Err(BookError::Line(1)));
+    }
+
+    #[test]
+    fn write_round_trip() {
+        let book = startpos_book();
+        let mut out = Vec::new();
+        book.write(&mut out).unwrap();
+        assert_eq!(Book::parse(&String::from_utf8(out).unwrap()), Ok(book));
+
+        let mut book = Book::default();
+        let book_move = BookMove {
+            mv: Move::pass(),
+            weight: 1,
+            wins: 1,
+            draws: 0,
+            losses: 0,
+        };
+        book.add(7, book_move);
+        let mut out = Vec::new();
+        book.write(&mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "7 0000 1 1 0 0\n");
+    }
+
+    #[test]
+    fn weighted_probe() {
+        let book = startpos_book();
+        let mut rng = Rng::with_seed(5);
+        let mut counts = [0; 2];
+
+        for _ in 0..400 {
+            let book_move = book.probe(&Position::default(), &mut rng).unwrap();
+            // Weight 0 and the illegal d4 are never played
+            match book_move.mv.to_string().as_str() {
+                "f2" => counts[0] += 1,
+                "b6" => counts[1] += 1,
+                other => panic!("Unexpected book move {}", other),
+            }
+        }
+        assert!(counts[0] > 2 * counts[1]);
+
+        let mut pos = Position::default();
+        pos.make_move(Move::new(12, 12));
+        assert_eq!(book.probe(&pos, &mut rng), None);
+    }
+}
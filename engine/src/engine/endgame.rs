@@ -0,0 +1,149 @@
+// Exact alpha-beta solver for the final stone difference, for positions
+// with so few empty squares that the rest of the game can be searched
+
+use super::{limits::Abort, moves::Move, policy};
+use crate::ataxx::position::{Position, Side};
+
+// Larger than any stone difference
+const WINDOW: i32 = 64;
+// Nodes between two looks at the clock and the stop flag
+const ABORT_INTERVAL: u64 = 1024;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct EndgameResult {
+    // Final stones of the side to move minus the opponent's
+    pub score: i32,
+    pub best_move: Move,
+}
+
+struct Solver<'a> {
+    nodes: u64,
+    budget: u64,
+    abort: Abort<'a>,
+}
+
+// None if the node budget or the time runs out, or the search is stopped,
+// before the position is solved
+pub fn solve(position: &Position, budget: u64, abort: Abort) -> Option<EndgameResult> {
+    if position.game_over() {
+        return None;
+    }
+
+    let mut solver = Solver {
+        nodes: 0,
+        budget,
+        abort,
+    };
+    let mut moves = position.generate_moves();
+    policy::order_moves(position, moves.as_mut_slice());
+
+    let mut alpha = -WINDOW;
+    let mut best_move = moves.data[0];
+    for mv in moves.as_slice().iter() {
+        let mut child = *position;
+        child.make_move(*mv);
+
+        let score = -solver.negamax(&child, -WINDOW, -alpha)?;
+        if score > alpha {
+            alpha = score;
+            best_move = *mv;
+        }
+    }
+
+    Some(EndgameResult {
+        score: alpha,
+        best_move,
+    })
+}
+
+impl Solver<'_> {
+    fn negamax(&mut self, position: &Position, mut alpha: i32, beta: i32) -> Option<i32> {
+        self.nodes += 1;
+        if self.nodes > self.budget
+            || (self.nodes.is_multiple_of(ABORT_INTERVAL) && self.abort.triggered())
+        {
+            return None;
+        }
+
+        // Covers the 100 ply rule as well
+        if position.game_over() {
+            return Some(stone_difference(position));
+        }
+
+        let mut moves = position.generate_moves();
+        policy::order_moves(position, moves.as_mut_slice());
+
+        for mv in moves.as_slice().iter() {
+            let mut child = *position;
+            child.make_move(*mv);
+
+            let score = -self.negamax(&child, -beta, -alpha)?;
+            if score >= beta {
+                return Some(score);
+            }
+            alpha = alpha.max(score);
+        }
+
+        Some(alpha)
+    }
+}
+
+fn stone_difference(position: &Position) -> i32 {
+    let black = position.black.popcnt() as i32;
+    let white = position.white.popcnt() as i32;
+
+    match position.turn {
+        Side::Black => black - white,
+        Side::White => white - black,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn solves_wipeout() {
+        let pos =
+            Position::from_fen("-------/-------/-------/-------/-------/1------/x1o---- x 0 1")
+                .unwrap();
+        let result = solve(&pos, 10_000, Abort::default()).unwrap();
+        assert_eq!(result.score, 3);
+        assert_eq!(result.best_move, Move::new(1, 1));
+    }
+
+    #[test]
+    fn solves_loss() {
+        // g1 loses by one, the jump to g1 lets white recapture
+        let pos =
+            Position::from_fen("-------/-------/-------/-------/-------/-------/ooooxx1 x 0 1")
+                .unwrap();
+        let result = solve(&pos, 10_000, Abort::default()).unwrap();
+        assert_eq!(result.score, -1);
+        assert_eq!(result.best_move, Move::new(6, 6));
+    }
+
+    #[test]
+    fn ply_limit() {
+        // Only one move is left before the 100 ply rule
+        let pos = Position::from_fen("x5o/7/7/7/7/7/o5x x 99 1").unwrap();
+        assert_eq!(solve(&pos, 10_000, Abort::default()).unwrap().score, 1);
+    }
+
+    #[test]
+    fn node_budget() {
+        assert_eq!(solve(&Position::default(), 1000, Abort::default()), None);
+    }
+
+    #[test]
+    fn deadline() {
+        let time = Instant::now();
+        let abort = Abort {
+            stop: None,
+            deadline: Some(time + Duration::from_millis(50)),
+        };
+        assert_eq!(solve(&Position::default(), u64::MAX, abort), None);
+        assert!(time.elapsed() < Duration::from_secs(2));
+    }
+}
@@ -0,0 +1,94 @@
+use super::params::Evaluation;
+use crate::ataxx::position::{Position, Side};
+
+// Every empty square a side can fill with a single is worth a quarter stone
+const MOBILITY_WEIGHT: f32 = 0.25;
+
+// Static evaluation in stones from white's point of view
+pub fn evaluate(position: &Position, kind: Evaluation) -> f32 {
+    let stones = position.white.popcnt() as f32 - position.black.popcnt() as f32;
+
+    match kind {
+        Evaluation::Stones => stones,
+        Evaluation::Static => {
+            let empty = position.empty_squares();
+            let mobility = |side: Side| (position.colored_squares(side).singles() & empty).popcnt();
+            let mobility_diff = mobility(Side::White) as f32 - mobility(Side::Black) as f32;
+
+            stones + MOBILITY_WEIGHT * mobility_diff
+        }
+    }
+}
+
+// Logistic conversion of an evaluation in stones, scale is the advantage
+// that gives roughly a 73% win probability
+pub fn win_probability(eval: f32, scale: f32) -> f32 {
+    1.0 / (1.0 + (-eval / scale).exp())
+}
+
+// Inverse of win_probability, the advantage in stones that the logistic
+// model expects to win with the given probability
+pub fn stone_advantage(probability: f32, scale: f32) -> f32 {
+    let p = probability.clamp(0.001, 0.999);
+    scale * (p / (1.0 - p)).ln()
+}
+
+// Final stone difference from white's point of view, scaled from 0 for a
+// black wipeout to 1 for a white one
+pub fn margin(position: &Position) -> f32 {
+    let white = position.white.popcnt() as f32;
+    let black = position.black.popcnt() as f32;
+    if white + black == 0.0 {
+        return 0.5;
+    }
+
+    0.5 + 0.5 * (white - black) / (white + black)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn symmetric_is_even() {
+        let pos = Position::default();
+        for kind in [Evaluation::Stones, Evaluation::Static] {
+            assert_eq!(evaluate(&pos, kind), 0.0);
+            assert_eq!(win_probability(evaluate(&pos, kind), 5.0), 0.5);
+        }
+    }
+
+    #[test]
+    fn evaluate_stones() {
+        let pos = Position::from_fen("x5o/7/7/7/7/7/oo4x x 0 1").unwrap();
+        assert_eq!(evaluate(&pos, Evaluation::Stones), 1.0);
+        assert!(evaluate(&pos, Evaluation::Static) > 1.0);
+    }
+
+    #[test]
+    fn win_probability_monotonic() {
+        assert!(win_probability(2.0, 5.0) > 0.5);
+        assert!(win_probability(-2.0, 5.0) < 0.5);
+        assert!(win_probability(10.0, 5.0) > win_probability(2.0, 5.0));
+        assert!((win_probability(3.0, 5.0) + win_probability(-3.0, 5.0) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn stone_advantage_inverts() {
+        assert_eq!(stone_advantage(0.5, 5.0), 0.0);
+        for eval in [-8.0, -1.5, 0.25, 3.0] {
+            let p = win_probability(eval, 5.0);
+            assert!((stone_advantage(p, 5.0) - eval).abs() < 1e-3);
+        }
+        assert!(stone_advantage(1.0, 5.0).is_finite());
+    }
+
+    #[test]
+    fn margin() {
+        let pos = Position::from_fen("x5o/7/7/7/7/7/o4xx x 100 1").unwrap();
+        assert_eq!(super::margin(&pos), 0.4);
+
+        let pos = Position::from_fen("7/7/7/7/7/7/x6 o 0 1").unwrap();
+        assert_eq!(super::margin(&pos), 0.0);
+    }
+}
@@ -0,0 +1,148 @@
+// Gumbel root search with sequential halving (Danihelka et al., 2022).
+// The top k moves by gumbel noise + logit are searched, and every phase
+// the worse half is dropped until the simulation budget runs out.
+
+use fastrand::Rng;
+
+// Constants of the monotone value transform sigma(q)
+const C_VISIT: f32 = 50.0;
+const C_SCALE: f32 = 1.0;
+
+pub struct SequentialHalving {
+    // Gumbel noise plus logit of every root child
+    scores: Vec<f32>,
+    candidates: Vec<usize>,
+    // Visits every candidate should have at the end of the current phase
+    phase_target: u32,
+    phases_left: u32,
+    budget: u32,
+    phases: u32,
+}
+
+impl SequentialHalving {
+    pub fn new(logits: &[f32], k: usize, budget: u32, rng: &mut Rng) -> Self {
+        let scores: Vec<f32> = logits.iter().map(|l| l + sample_gumbel(rng)).collect();
+
+        let mut candidates: Vec<usize> = (0..scores.len()).collect();
+        candidates.sort_by(|a, b| scores[*b].total_cmp(&scores[*a]));
+        candidates.truncate(k.max(1));
+
+        let phases = (candidates.len() as f32).log2().ceil().max(1.0) as u32;
+        let mut halving = SequentialHalving {
+            scores,
+            candidates,
+            phase_target: 0,
+            phases_left: phases,
+            budget,
+            phases,
+        };
+        halving.phase_target = halving.phase_visits();
+
+        halving
+    }
+
+    fn phase_visits(&self) -> u32 {
+        (self.budget / (self.phases * self.candidates.len() as u32)).max(1)
+    }
+
+    // Next root child to simulate, the least visited candidate of the phase.
+    // Returns None once the last phase is complete.
+    pub fn next_child(&mut self, visits: &[u32], q: &[f32]) -> Option<usize> {
+        loop {
+            let child = *self.candidates.iter().min_by_key(|c| visits[**c]).unwrap();
+            if visits[child] < self.phase_target {
+                return Some(child);
+            }
+
+            if self.phases_left <= 1 || self.candidates.len() == 1 {
+                return None;
+            }
+
+            self.halve(visits, q);
+        }
+    }
+
+    fn halve(&mut self, visits: &[u32], q: &[f32]) {
+        let max_visits = *visits.iter().max().unwrap_or(&0);
+        let mut ranked = self.candidates.clone();
+        ranked.sort_by(|a, b| {
+            let score = |c: usize| self.scores[c] + sigma(q[c], max_visits);
+            score(*b).total_cmp(&score(*a))
+        });
+        ranked.truncate(self.candidates.len().div_ceil(2));
+
+        self.candidates = ranked;
+        self.phases_left -= 1;
+        self.phase_target += self.phase_visits();
+    }
+
+    // Final choice among the remaining candidates
+    pub fn best(&self, visits: &[u32], q: &[f32]) -> usize {
+        let max_visits = *visits.iter().max().unwrap_or(&0);
+        *self
+            .candidates
+            .iter()
+            .max_by(|a, b| {
+                let score = |c: usize| self.scores[c] + sigma(q[c], max_visits);
+                score(**a).total_cmp(&score(**b))
+            })
+            .unwrap()
+    }
+
+    #[cfg(test)]
+    pub fn candidates(&self) -> &[usize] {
+        &self.candidates
+    }
+}
+
+// Monotone transform of a value in [0, 1], grows with the visit count so
+// the value outweighs the prior once the candidates are well searched
+fn sigma(q: f32, max_visits: u32) -> f32 {
+    (C_VISIT + max_visits as f32) * C_SCALE * q
+}
+
+fn sample_gumbel(rng: &mut Rng) -> f32 {
+    // Avoid ln(0)
+    let u = rng.f32().max(f32::MIN_POSITIVE);
+    -(-u.ln()).ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn top_k_candidates() {
+        // Logits far apart so the noise can't reorder them
+        let logits = [0.0, 100.0, 50.0, 200.0, -100.0];
+        let halving = SequentialHalving::new(&logits, 3, 100, &mut Rng::new());
+        assert_eq!(halving.candidates(), &[3, 1, 2]);
+    }
+
+    #[test]
+    fn halving_schedule() {
+        let logits = [0.0; 8];
+        let mut halving = SequentialHalving::new(&logits, 8, 96, &mut Rng::new());
+        let mut visits = [0; 8];
+        // Value only separates the children once the prior is flat
+        let q = [0.0, 1.0, 0.0, 0.9, 0.0, 0.5, 0.0, 0.4];
+        let mut simulations = 0;
+
+        while let Some(child) = halving.next_child(&visits, &q) {
+            visits[child] += 1;
+            simulations += 1;
+        }
+
+        // 3 phases: 8 x 4, 4 x 8, 2 x 16 visits
+        assert_eq!(simulations, 96);
+        assert_eq!(halving.candidates().len(), 2);
+        let best = halving.best(&visits, &q);
+        assert!(best == 1 || best == 3);
+    }
+
+    #[test]
+    fn sigma_is_monotone() {
+        assert!(sigma(0.6, 10) > sigma(0.5, 10));
+        assert!(sigma(0.6, 100) - sigma(0.5, 100) > sigma(0.6, 10) - sigma(0.5, 10));
+    }
+}
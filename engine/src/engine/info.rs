@@ -0,0 +1,165 @@
+// UAI info output while searching and once at the end
+
+use super::{eval, moves::Move, proof::Proof};
+use std::fmt::Display;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Score {
+    Cp(i32),
+    // Full moves, positive if the side to move wins
+    Mate(i32),
+}
+
+// A root move with the line the search expects after it
+#[derive(Clone, Debug, PartialEq)]
+pub struct PvLine {
+    pub score: Score,
+    // Win, draw and loss in permille, when asked for
+    pub wdl: Option<[u32; 3]>,
+    pub visits: u32,
+    // Starts with the root move
+    pub pv: Vec<Move>,
+}
+
+// Counters of the whole search
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SearchStats {
+    // Average depth of the simulations, and the deepest one
+    pub depth: u32,
+    pub seldepth: u32,
+    pub nodes: u64,
+    // Milliseconds
+    pub time: u128,
+    // Node pool usage in permille
+    pub hashfull: u32,
+}
+
+impl Score {
+    // Value is the expected score for the side to move, proofs are seen
+    // from the side that moved into the node. Centipawns are hundredths of
+    // the stone advantage that truncated rollouts would score with the same
+    // value, so they follow the EvalScale option.
+    pub fn new(value: f32, proof: Proof, scale: f32) -> Score {
+        match proof {
+            Proof::Draw(_) => Score::Cp(0),
+            proof => match proof.mate_score() {
+                Some(mate) => Score::Mate(mate),
+                None => Score::Cp((100.0 * eval::stone_advantage(value, scale)).round() as i32),
+            },
+        }
+    }
+}
+
+impl Display for Score {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Score::Cp(cp) => write!(f, "cp {}", cp),
+            Score::Mate(moves) => write!(f, "mate {}", moves),
+        }
+    }
+}
+
+// Win, draw and loss for the side to move in permille. The value counts a
+// draw as half a win, so the draw rate is capped to keep both ends
+// non-negative.
+pub fn wdl(value: f32, draw_rate: f32, proof: Proof) -> [u32; 3] {
+    match proof {
+        Proof::Loss(_) => return [1000, 0, 0],
+        Proof::Win(_) => return [0, 0, 1000],
+        Proof::Draw(_) => return [0, 1000, 0],
+        Proof::Unknown => (),
+    }
+
+    let value = value.clamp(0.0, 1.0);
+    let draw = draw_rate.clamp(0.0, 2.0 * value.min(1.0 - value));
+    let win = ((value - draw / 2.0) * 1000.0).round() as u32;
+    let draw = ((draw * 1000.0).round() as u32).min(1000 - win);
+
+    [win, draw, 1000 - win - draw]
+}
+
+// One line per root move, the best first
+pub fn print_info(stats: &SearchStats, lines: &[PvLine]) {
+    for (i, line) in lines.iter().enumerate() {
+        println!("{}", info_line(stats, i + 1, line));
+    }
+}
+
+fn info_line(stats: &SearchStats, multipv: usize, line: &PvLine) -> String {
+    let nps = stats.nodes as u128 * 1000 / stats.time.max(1);
+    let pv: Vec<String> = line.pv.iter().map(Move::to_string).collect();
+    let wdl = match line.wdl {
+        Some([w, d, l]) => format!(" wdl {} {} {}", w, d, l),
+        None => String::new(),
+    };
+
+    format!(
+        "info depth {} seldepth {} multipv {} score {}{} nodes {} nps {} time {} hashfull {} pv {}",
+        stats.depth,
+        stats.seldepth,
+        multipv,
+        line.score,
+        wdl,
+        stats.nodes,
+        nps,
+        stats.time,
+        stats.hashfull,
+        pv.join(" ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores() {
+        assert_eq!(Score::new(0.5, Proof::Unknown, 5.0), Score::Cp(0));
+        // A stone at scale 1 is worth e to 1
+        let p = 1.0_f32.exp() / (1.0 + 1.0_f32.exp());
+        assert_eq!(Score::new(p, Proof::Unknown, 1.0), Score::Cp(100));
+        assert_eq!(Score::new(1.0 - p, Proof::Unknown, 2.0), Score::Cp(-200));
+        assert_eq!(Score::new(1.0, Proof::Loss(3), 5.0), Score::Mate(2));
+        assert_eq!(Score::new(0.0, Proof::Win(2), 5.0), Score::Mate(-1));
+        assert_eq!(Score::new(0.5, Proof::Draw(4), 5.0), Score::Cp(0));
+
+        assert_eq!(Score::Cp(-12).to_string(), "cp -12");
+        assert_eq!(Score::Mate(3).to_string(), "mate 3");
+    }
+
+    #[test]
+    fn wdl_permille() {
+        assert_eq!(wdl(0.5, 0.2, Proof::Unknown), [400, 200, 400]);
+        assert_eq!(wdl(0.75, 0.1, Proof::Unknown), [700, 100, 200]);
+        // More draws than the value allows
+        assert_eq!(wdl(0.9, 0.5, Proof::Unknown), [800, 200, 0]);
+        assert_eq!(wdl(0.3, 0.0, Proof::Loss(5)), [1000, 0, 0]);
+        assert_eq!(wdl(0.3, 0.0, Proof::Draw(5)), [0, 1000, 0]);
+    }
+
+    #[test]
+    fn info_lines() {
+        let stats = SearchStats {
+            depth: 4,
+            seldepth: 9,
+            nodes: 3000,
+            time: 1500,
+            hashfull: 12,
+        };
+        let mut line = PvLine {
+            score: Score::Cp(-8),
+            wdl: None,
+            visits: 900,
+            pv: vec![Move::new(12, 12), Move::new(48, 34)],
+        };
+
+        assert_eq!(
+            info_line(&stats, 2, &line),
+            "info depth 4 seldepth 9 multipv 2 score cp -8 nodes 3000 nps 2000 time 1500 \
+             hashfull 12 pv f2 g7g5"
+        );
+
+        line.wdl = Some([300, 200, 500]);
+        assert!(info_line(&stats, 1, &line).contains("score cp -8 wdl 300 200 500 nodes"));
+    }
+}
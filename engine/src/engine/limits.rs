@@ -0,0 +1,133 @@
+use super::moves::Move;
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Instant,
+};
+
+// Longest search, in multiples of the time budget, while final moves disagree
+const MAX_EXTENSION: u128 = 2;
+// Kept back from the clock for communication with the GUI
+const MOVE_OVERHEAD: u128 = 50;
+// Moves the remaining clock is spread over when the GUI doesn't say
+const DEFAULT_MOVES_TO_GO: u128 = 20;
+
+// When a search has to stop, every limit that is set applies. Without any
+// limit the search only ends once the root is solved.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SearchLimits {
+    // Milliseconds
+    pub move_time: Option<u128>,
+    // Simulations, counted exactly over all threads
+    pub nodes: Option<u64>,
+    // Average depth of the simulations
+    pub depth: Option<u32>,
+    // Root moves the search is restricted to, empty for all
+    pub search_moves: Vec<Move>,
+    // Clock of the side to move, in milliseconds
+    pub time_left: Option<u128>,
+    pub increment: u128,
+    pub moves_to_go: Option<u32>,
+}
+
+// Lets the solvers that run outside the tree search give up once the
+// search is stopped or its time is up. The default never triggers.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Abort<'a> {
+    pub stop: Option<&'a AtomicBool>,
+    pub deadline: Option<Instant>,
+}
+
+impl SearchLimits {
+    pub fn from_time(move_time: u128) -> Self {
+        SearchLimits {
+            move_time: Some(move_time),
+            ..SearchLimits::default()
+        }
+    }
+
+    pub fn from_nodes(nodes: u64) -> Self {
+        SearchLimits {
+            nodes: Some(nodes),
+            ..SearchLimits::default()
+        }
+    }
+
+    // The tighter of the move time and the clock's share for this move
+    pub fn time_budget(&self) -> Option<u128> {
+        let clock = self.time_left.map(|time_left| {
+            let usable = time_left.saturating_sub(MOVE_OVERHEAD);
+            let moves_to_go = self
+                .moves_to_go
+                .map_or(DEFAULT_MOVES_TO_GO, |m| m.max(1) as u128);
+
+            // Even a fully extended search leaves half the clock
+            (usable / moves_to_go + self.increment).min(usable / (2 * MAX_EXTENSION))
+        });
+
+        match (self.move_time, clock) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    // Longest the search may take. A share of the clock may be extended,
+    // a move time set by the GUI may not.
+    pub fn max_time(&self) -> Option<u128> {
+        let extended = MAX_EXTENSION * self.time_budget()?;
+        Some(
+            self.move_time
+                .map_or(extended, |move_time| move_time.min(extended)),
+        )
+    }
+
+    // Only a stop, or a solved root, ends the search
+    pub fn is_infinite(&self) -> bool {
+        self.move_time.is_none()
+            && self.nodes.is_none()
+            && self.depth.is_none()
+            && self.time_left.is_none()
+    }
+}
+
+impl Abort<'_> {
+    pub fn triggered(&self) -> bool {
+        self.stop.is_some_and(|stop| stop.load(Ordering::Relaxed))
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_budget() {
+        assert_eq!(SearchLimits::default().time_budget(), None);
+        assert_eq!(SearchLimits::from_time(300).time_budget(), Some(300));
+
+        let clock = SearchLimits {
+            time_left: Some(10_050),
+            increment: 100,
+            ..SearchLimits::default()
+        };
+        assert_eq!(clock.time_budget(), Some(600));
+        assert_eq!(clock.max_time(), Some(1200));
+
+        let last_move = SearchLimits {
+            moves_to_go: Some(1),
+            ..clock.clone()
+        };
+        assert_eq!(last_move.time_budget(), Some(2500));
+
+        let both = SearchLimits {
+            move_time: Some(200),
+            ..clock
+        };
+        assert_eq!(both.time_budget(), Some(200));
+        assert_eq!(both.max_time(), Some(200));
+        assert!(!both.is_infinite());
+        assert!(SearchLimits::default().is_infinite());
+    }
+}
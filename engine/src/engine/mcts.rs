@@ -1,148 +1,894 @@
-use super::moves::Move;
-use crate::ataxx::position::{Outcome, Position, Side};
-use std::{cell::RefCell, f32::consts::SQRT_2, rc::Rc, time::Instant};
+use super::{
+    atomic::AtomicF32,
+    endgame,
+    gumbel::SequentialHalving,
+    info::{self, PvLine, Score, SearchStats},
+    limits::{Abort, SearchLimits},
+    moves::Move,
+    noise,
+    params::{FinalMove, ParallelMode, RootSelection, SearchParams, Selection},
+    pns::{self, MateResult},
+    policy,
+    proof::{AtomicProof, Proof},
+    rave::AmafMoves,
+    rollout::{self, Playout},
+};
+use crate::ataxx::{
+    position::{Position, Side},
+    symmetry,
+};
+use fastrand::Rng;
+use std::{
+    collections::HashMap,
+    f32::consts::SQRT_2,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+    time::{Duration, Instant},
+};
 
 const INFINITY: f32 = 10_000_000.0;
 const C: f32 = SQRT_2;
 const NODEPOOL_MAX_MEM: usize = 2 * 1024 * 1024 * 1024; // 2GB
+const NODEPOOL_SIZE: usize =
+    NODEPOOL_MAX_MEM / (std::mem::size_of::<Node>() + std::mem::size_of::<Edge>());
+const TABLE_SHARDS: usize = 64;
+const INFO_INTERVAL: Duration = Duration::from_secs(1);
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Debug)]
 struct Node {
-    idx: usize,
-    parent: Option<usize>,
-    children: Rc<RefCell<Vec<usize>>>,
-    visits: usize,
-    total_value: f32,
+    // All edges are created at once on expansion and never move afterwards,
+    // so threads can hold references into the tree without locking
+    children: OnceLock<Box<[Edge]>>,
+    visits: AtomicU32,
+    virtual_loss: AtomicU32,
+    // Claimed by the first simulation to reach the node, which rolls it out
+    rolled_out: AtomicBool,
+    total_value: AtomicF32,
+    // Share of the simulations that ended in a draw, summed like values
+    draws: AtomicF32,
+    proof: AtomicProof,
     position: Position,
-    from_action: Move,
+}
+
+// A move from a parent to its child. In graph search several edges can lead
+// to the same node, so everything that belongs to the move is kept here.
+// The edge counts the simulations that went through this parent, the node
+// those of all its parents together.
+#[derive(Debug)]
+struct Edge {
+    mv: Move,
+    prior: f32,
+    visits: AtomicU32,
+    total_value: AtomicF32,
+    draws: AtomicF32,
+    // All-moves-as-first statistics, from the same side as total_value
+    amaf_visits: AtomicU32,
+    amaf_value: AtomicF32,
+    node: Arc<Node>,
+    // Takes the parent's board to the one stored in the node, whose moves
+    // are in that frame. Only symmetry hashing shares nodes across frames.
+    sym: usize,
+}
+
+// Nodes of the search graph by position, sharded to keep threads apart
+struct TranspositionTable {
+    shards: Vec<Mutex<HashMap<u64, Arc<Node>>>>,
 }
 
 pub struct Tree {
-    nodes: Vec<Node>,
+    root: Node,
+    node_count: AtomicUsize,
+    capacity: usize,
+    params: SearchParams,
+    gumbel: Mutex<Option<SequentialHalving>>,
+    table: Option<TranspositionTable>,
+    // Seeded per search, every thread forks its own generator from it
+    rng: Mutex<Rng>,
+    // Simulations claimed so far, for exact node limits
+    simulations: AtomicU64,
+    // Summed and maximum depth of the simulations, for info output
+    depth_sum: AtomicU64,
+    seldepth: AtomicU32,
+    // Final stone difference for the side to move, if the endgame solver ran
+    exact_score: Option<i32>,
+    // Prints info lines while searching, tools search silently
+    info: bool,
+    // Legal root moves the search is restricted to, empty for all
+    search_moves: Vec<Move>,
+    // Set from another thread to end the search early, it stays set
+    stop: Arc<AtomicBool>,
 }
 
 impl Tree {
+    #[allow(dead_code)]
     pub fn new() -> Self {
-        const NODEPOOL_SIZE: usize = NODEPOOL_MAX_MEM / std::mem::size_of::<Node>();
-        let v = Vec::with_capacity(NODEPOOL_SIZE);
-        Tree { nodes: v }
+        Tree::with_params(SearchParams::default())
+    }
+
+    pub fn with_params(params: SearchParams) -> Self {
+        Tree {
+            root: Node::new(Position::default()),
+            node_count: AtomicUsize::new(1),
+            capacity: NODEPOOL_SIZE,
+            params,
+            gumbel: Mutex::new(None),
+            table: None,
+            rng: Mutex::new(Rng::new()),
+            simulations: AtomicU64::new(0),
+            depth_sum: AtomicU64::new(0),
+            seldepth: AtomicU32::new(0),
+            exact_score: None,
+            info: false,
+            search_moves: Vec::new(),
+            stop: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn set_info(&mut self, info: bool) {
+        self.info = info;
+    }
+
+    // Handle to stop the search from another thread
+    pub fn stop_signal(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    fn stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
     }
 
-    pub fn uct(&mut self, pos: Position, move_time: u128) -> Move {
+    pub fn uct(&mut self, pos: Position, limits: SearchLimits) -> Move {
         let time = Instant::now();
-        let moves = pos.generate_moves();
+        self.exact_score = None;
+        // The game is over, even at the move limit where moves are still
+        // generated, and 0000 is the only answer
+        if pos.game_over() {
+            return Move::pass();
+        }
+
+        let mut moves = pos.generate_moves();
+        self.search_moves = limits.search_moves.clone();
+        self.search_moves.retain(|mv| moves.as_slice().contains(mv));
+        if !self.search_moves.is_empty() {
+            moves.retain(|mv| self.search_moves.contains(mv));
+        }
         if moves.len() == 1 {
             return moves.data[0];
         }
 
-        let mut root = Node::new();
-        root.position = pos;
-        self.nodes.push(root.clone());
+        self.reset(pos);
+
+        // The solver and the mate check know nothing of searchmoves
+        let restricted = !self.search_moves.is_empty();
+
+        // The solver gets half the time budget, so the search still has the
+        // other half if it fails
+        if !restricted && pos.empty_squares().popcnt() < self.params.endgame_empties {
+            let abort = Abort {
+                stop: Some(&self.stop),
+                deadline: limits
+                    .time_budget()
+                    .map(|budget| time + Duration::from_millis((budget / 2) as u64)),
+            };
+            if let Some(result) = endgame::solve(&pos, self.params.endgame_nodes, abort) {
+                self.exact_score = Some(result.score);
+                return result.best_move;
+            }
+        }
+
+        // A stopped search may not get any simulation done, there still has
+        // to be a move to fall back on
+        self.root.expand(self);
 
-        while time.elapsed().as_millis() < move_time {
-            let selection = self.tree_policy(root.clone());
-            let value = selection.default_policy();
-            self.backup_negamax(selection.idx, value);
+        if self.params.parallel == ParallelMode::Root && self.params.threads > 1 {
+            self.root_parallel(pos, time, &limits);
+        } else {
+            self.tree_parallel(time, &limits);
         }
 
-        let best_move = self.best_move();
+        let mut best_move = self.best_move();
         debug_assert_ne!(best_move, Move::null(), "No best move found");
-        // self.confirm_logic();
+
+        // The mate check may use what is left of the longest search time,
+        // a stopped search has to answer right away
+        let abort = Abort {
+            stop: Some(&self.stop),
+            deadline: limits
+                .max_time()
+                .map(|max_time| time + Duration::from_millis(max_time as u64)),
+        };
+
+        // A root proven won for the side to move already plays the quickest win
+        if self.params.mate_check
+            && !restricted
+            && !abort.triggered()
+            && !matches!(self.root.proof(), Proof::Loss(_))
+        {
+            let result = pns::find_mate(
+                &pos,
+                self.params.mate_check_moves,
+                self.params.pns_memory,
+                abort,
+            );
+            if let MateResult::Mate(_, mv) = result {
+                best_move = mv;
+            }
+        }
+
+        if self.info {
+            self.print_info(time, Some(best_move));
+        }
 
         best_move
     }
 
-    fn tree_policy(&mut self, mut node: Node) -> Node {
-        while !node.is_terminal() {
-            if node.is_expandable() {
-                node = node.expand(self);
+    fn reset(&mut self, pos: Position) {
+        self.root = Node::new(pos);
+        self.node_count.store(1, Ordering::Relaxed);
+        self.simulations.store(0, Ordering::Relaxed);
+        self.depth_sum.store(0, Ordering::Relaxed);
+        self.seldepth.store(0, Ordering::Relaxed);
+        *self.gumbel.get_mut().unwrap() = None;
+        self.table = self.params.graph.then(TranspositionTable::new);
+        *self.rng.get_mut().unwrap() = search_rng(self.params.seed, &pos);
+
+        let epsilon = self.params.dirichlet_epsilon;
+        let mut root_noise = None;
+        let use_noise = self.params.root_noise && self.params.root_priors();
+        if self.params.root_noise && !use_noise && self.info {
+            println!("info string RootNoise needs PUCT selection or a Gumbel root, ignored");
+        }
+        if use_noise && self.root.expand(self).is_some() {
+            let children = self.root.children.get_mut().unwrap();
+            let rng = self.rng.get_mut().unwrap();
+            let noise = noise::dirichlet(rng, self.params.dirichlet_alpha, children.len());
+
+            for (child, noise) in children.iter_mut().zip(noise.iter()) {
+                child.prior = (1.0 - epsilon) * child.prior + epsilon * noise;
+            }
+            root_noise = Some(noise);
+        }
+
+        if self.params.root_selection == RootSelection::Gumbel {
+            let children = self.root.expand(self).unwrap();
+            let mut logits: Vec<f32> = children
+                .iter()
+                .map(|c| policy::move_logit(&pos, c.mv))
+                .collect();
+
+            // The noise goes into the probabilities the logits stand for
+            if let Some(noise) = root_noise {
+                logits = policy::softmax(&logits)
+                    .into_iter()
+                    .zip(noise)
+                    .map(|(p, noise)| {
+                        ((1.0 - epsilon) * p + epsilon * noise)
+                            .max(f32::MIN_POSITIVE)
+                            .ln()
+                    })
+                    .collect();
+            }
+            let halving = SequentialHalving::new(
+                &logits,
+                self.params.gumbel_k,
+                self.params.gumbel_simulations,
+                self.rng.get_mut().unwrap(),
+            );
+
+            *self.gumbel.get_mut().unwrap() = Some(halving);
+        }
+    }
+
+    fn tree_parallel(&self, time: Instant, limits: &SearchLimits) {
+        let mut rngs: Vec<Rng> = {
+            let mut rng = self.rng.lock().unwrap();
+            (0..self.params.threads).map(|_| rng.fork()).collect()
+        };
+        let mut main_rng = rngs.pop().unwrap();
+
+        std::thread::scope(|s| {
+            for mut rng in rngs {
+                s.spawn(move || self.search(time, limits, &mut rng, false));
+            }
+            self.search(time, limits, &mut main_rng, self.info);
+        });
+    }
+
+    fn root_parallel(&mut self, pos: Position, time: Instant, limits: &SearchLimits) {
+        let threads = self.params.threads;
+
+        // Seeded trees need seeds of their own, or they would all search alike
+        let params: Vec<SearchParams> = {
+            let rng = self.rng.get_mut().unwrap();
+            (0..threads)
+                .map(|_| SearchParams {
+                    threads: 1,
+                    seed: if self.params.seed == 0 {
+                        0
+                    } else {
+                        rng.u64(1..)
+                    },
+                    ..self.params.clone()
+                })
+                .collect()
+        };
+
+        let trees: Vec<Tree> = std::thread::scope(|s| {
+            let handles: Vec<_> = params
+                .into_iter()
+                .enumerate()
+                .map(|(i, params)| {
+                    // Every tree gets its share of the node limit
+                    let limits = SearchLimits {
+                        nodes: limits
+                            .nodes
+                            .map(|n| n / threads as u64 + ((i as u64) < n % threads as u64) as u64),
+                        ..limits.clone()
+                    };
+                    let search_moves = self.search_moves.clone();
+                    let stop = self.stop.clone();
+                    // The first tree reports for all of them while searching
+                    let report = self.info && i == 0;
+
+                    s.spawn(move || {
+                        let mut tree = Tree::with_params(params);
+                        tree.capacity = NODEPOOL_SIZE / threads;
+                        tree.search_moves = search_moves;
+                        tree.stop = stop;
+                        tree.reset(pos);
+                        let mut rng = tree.rng.get_mut().unwrap().fork();
+                        tree.search(time, &limits, &mut rng, report);
+                        tree
+                    })
+                })
+                .collect();
+
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        // The merged visits come from independent halving schedules,
+        // so the final choice is left to the regular root policy
+        *self.gumbel.get_mut().unwrap() = None;
+
+        // Every tree generates the root children in the same order,
+        // so the statistics can be merged child by child. Each root move
+        // takes over the node of the tree that searched it the most, whose
+        // subtree gives the PV, and the other trees are added to it.
+        self.root.expand(self);
+        let mut searched_most = Vec::new();
+        for (i, child) in self.root.children.get_mut().unwrap().iter_mut().enumerate() {
+            let best = trees
+                .iter()
+                .enumerate()
+                .filter_map(|(t, tree)| Some((t, &tree.root.children.get()?[i])))
+                .max_by_key(|(_, other)| other.visits());
+            if let Some((_, other)) = best {
+                child.node = other.node.clone();
+            }
+            searched_most.push(best.map(|(t, _)| t));
+        }
+
+        let children = self.root.children.get().unwrap();
+        for (t, tree) in trees.iter().enumerate() {
+            let Some(other_children) = tree.root.children.get() else {
+                continue;
+            };
+
+            for (i, (child, other)) in children.iter().zip(other_children.iter()).enumerate() {
+                debug_assert_eq!(child.mv, other.mv);
+                child.visits.fetch_add(other.visits(), Ordering::Relaxed);
+                child.total_value.fetch_add(other.total_value.load());
+                child.draws.fetch_add(other.draws.load());
+
+                // Mirrored moves can share a node, it is only merged once
+                if searched_most[i] == Some(t)
+                    || children[..i]
+                        .iter()
+                        .any(|c| Arc::ptr_eq(&c.node, &child.node))
+                {
+                    continue;
+                }
+                let (node, other) = (&child.node, &other.node);
+                node.visits.fetch_add(other.visits(), Ordering::Relaxed);
+                node.total_value.fetch_add(other.total_value.load());
+                node.draws.fetch_add(other.draws.load());
+            }
+
+            self.root
+                .visits
+                .fetch_add(tree.root.visits(), Ordering::Relaxed);
+            self.root
+                .total_value
+                .fetch_add(tree.root.total_value.load());
+            self.root.draws.fetch_add(tree.root.draws.load());
+
+            self.node_count
+                .fetch_add(tree.node_count.load(Ordering::Relaxed), Ordering::Relaxed);
+            self.depth_sum
+                .fetch_add(tree.depth_sum.load(Ordering::Relaxed), Ordering::Relaxed);
+            self.seldepth
+                .fetch_max(tree.seldepth.load(Ordering::Relaxed), Ordering::Relaxed);
+        }
+
+        // Proofs are exact, so whatever one tree solved holds for all of them
+        for (i, child) in children.iter().enumerate() {
+            let proof = trees
+                .iter()
+                .filter_map(|t| t.root.children.get())
+                .map(|c| c[i].proof())
+                .find(Proof::is_solved);
+
+            if let Some(proof) = proof {
+                child.node.proof.store(proof);
+            }
+        }
+        self.root.update_proof(children);
+    }
+
+    // Only one thread reports, under root parallelism for its own tree
+    fn search(&self, time: Instant, limits: &SearchLimits, rng: &mut Rng, report: bool) {
+        let mut last_report = Instant::now();
+        let times = limits.time_budget().zip(limits.max_time());
+
+        while !self.root.proof().is_solved() && !self.stopped() {
+            if report && last_report.elapsed() >= INFO_INTERVAL {
+                self.print_info(time, None);
+                last_report = Instant::now();
+            }
+
+            if let Some((budget, max_time)) = times {
+                let elapsed = time.elapsed().as_millis();
+                if elapsed >= max_time || (elapsed >= budget && self.final_moves_agree()) {
+                    break;
+                }
+            }
+
+            if let Some(depth) = limits.depth {
+                let simulations = self.root.visits() as u64;
+                if simulations > 0
+                    && self.depth_sum.load(Ordering::Relaxed) / simulations >= depth as u64
+                {
+                    break;
+                }
+            }
+
+            if !self.claim_simulation(limits) {
+                break;
+            }
+
+            let Some(path) = self.tree_policy() else {
+                break;
+            };
+            let depth = path.len() as u32 - 1;
+            self.depth_sum.fetch_add(depth as u64, Ordering::Relaxed);
+            self.seldepth.fetch_max(depth, Ordering::Relaxed);
+
+            let (leaf, _) = path.last().unwrap();
+            let mut amaf = AmafMoves::default();
+            let (value, draws) = match leaf.proof() {
+                Proof::Unknown => leaf.default_policy(&self.params, &mut amaf, rng),
+                exact => (
+                    exact.value().unwrap(),
+                    f32::from(matches!(exact, Proof::Draw(_))),
+                ),
+            };
+            self.backup_negamax(&path, value, draws, &mut amaf);
+        }
+    }
+
+    fn claim_simulation(&self, limits: &SearchLimits) -> bool {
+        limits
+            .nodes
+            .is_none_or(|nodes| self.simulations.fetch_add(1, Ordering::Relaxed) < nodes)
+    }
+
+    // Nodes from the root down to the leaf, with the edge into each of them.
+    // None once a Gumbel root has used up its simulation budget.
+    fn tree_policy(&self) -> Option<Vec<(&Node, Option<&Edge>)>> {
+        let mut node = &self.root;
+        node.add_virtual_loss(self.params.virtual_loss);
+        let mut path = vec![(node, None)];
+
+        while !node.proof().is_solved() {
+            // A full node pool turns the node into a permanent leaf
+            let Some(children) = node.expand(self) else {
                 break;
+            };
+
+            let edge = if path.len() == 1 && self.params.root_selection == RootSelection::Gumbel {
+                match self.gumbel_child(children) {
+                    Some(child) => child,
+                    None => {
+                        self.root
+                            .virtual_loss
+                            .fetch_sub(self.params.virtual_loss, Ordering::Relaxed);
+                        return None;
+                    }
+                }
             } else {
-                let node_idx = node.best_child(self);
-                node = self.nodes[node_idx].clone();
+                node.best_child(children, &self.params)
+            };
+            node = &edge.node;
+            node.add_virtual_loss(self.params.virtual_loss);
+            path.push((node, Some(edge)));
+
+            // Simulations arriving before the first result is backed up go
+            // on through the node instead of rolling it out again
+            if !node.rolled_out.swap(true, Ordering::Relaxed) {
+                break;
             }
         }
 
-        node
+        Some(path)
     }
 
-    fn backup_negamax(&mut self, mut node_idx: usize, mut delta: f32) {
-        let mut node = &mut self.nodes[node_idx];
-        debug_assert!(!node.is_expanded());
+    fn gumbel_child<'a>(&self, children: &'a [Edge]) -> Option<&'a Edge> {
+        let (visits, q) = root_statistics(children);
+        let mut gumbel = self.gumbel.lock().unwrap();
+        let idx = gumbel.as_mut().unwrap().next_child(&visits, &q)?;
 
-        loop {
-            node.visits += 1;
-            node.total_value += delta;
-            debug_assert!(0.0 <= node.total_value && node.total_value as usize <= node.visits);
+        Some(&children[idx])
+    }
 
-            delta = 1.0 - delta;
-            node_idx = node.parent.unwrap();
-            node = &mut self.nodes[node_idx];
+    // Only the nodes and edges on the path are updated, so a node shared by
+    // several parents in graph search still gets exactly one visit per
+    // simulation, credited to the edge it was reached through
+    fn backup_negamax(
+        &self,
+        path: &[(&Node, Option<&Edge>)],
+        mut delta: f32,
+        draws: f32,
+        amaf: &mut AmafMoves,
+    ) {
+        let mut solved = path.last().unwrap().0.proof().is_solved();
 
-            // Root
-            if node_idx == 0 {
-                node.visits += 1;
-                node.total_value += delta;
-                break;
+        for (depth, (node, edge)) in path.iter().enumerate().rev() {
+            // A freshly solved child may solve its parent as well
+            if solved && depth > 0 {
+                let (parent, _) = path[depth - 1];
+                solved = parent.update_proof(parent.children.get().unwrap());
+            }
+
+            node.visits.fetch_add(1, Ordering::Relaxed);
+            node.total_value.fetch_add(delta);
+            node.draws.fetch_add(draws);
+            node.virtual_loss
+                .fetch_sub(self.params.virtual_loss, Ordering::Relaxed);
+            debug_assert!(
+                0.0 <= node.total_value.load() && node.total_value.load() <= node.visits() as f32
+            );
+
+            if let Some(edge) = edge {
+                edge.visits.fetch_add(1, Ordering::Relaxed);
+                edge.total_value.fetch_add(delta);
+                edge.draws.fetch_add(draws);
+            }
+
+            // amaf holds every move played below this node at this point,
+            // on the node's board, and is taken back to the parent's board
+            if self.params.rave {
+                node.update_amaf(amaf, 1.0 - delta);
+                if let Some(edge) = edge {
+                    if edge.sym != 0 {
+                        *amaf = amaf.transform(symmetry::inverse(edge.sym));
+                    }
+                    amaf.record(!node.position.turn, edge.mv);
+                }
             }
+
+            delta = 1.0 - delta;
         }
     }
 
     pub fn best_move(&self) -> Move {
-        debug_assert!(!self.nodes.is_empty());
-        let root = &self.nodes[0];
-        let mut best_value = -INFINITY;
-        let mut best_move = Move::null();
+        let Some(children) = self.root.children.get() else {
+            return Move::null();
+        };
+
+        // Play the quickest proven win, or resist a proven loss the longest
+        let proven = match self.root.proof() {
+            Proof::Loss(_) => children
+                .iter()
+                .filter_map(|c| match c.proof() {
+                    Proof::Win(plies) => Some((c, plies)),
+                    _ => None,
+                })
+                .min_by_key(|(_, plies)| *plies),
+            Proof::Win(_) => children
+                .iter()
+                .filter_map(|c| match c.proof() {
+                    Proof::Loss(plies) => Some((c, plies)),
+                    _ => None,
+                })
+                .max_by_key(|(_, plies)| *plies),
+            _ => None,
+        };
 
-        let children = (*root.children).borrow();
+        if let Some((child, _)) = proven {
+            return child.mv;
+        }
 
-        for child_idx in children.iter() {
-            let child = &self.nodes[*child_idx];
-            let avg_val = child.total_value / child.visits as f32;
+        if self.root.position.half_moves < self.params.temperature_plies {
+            let candidates: Vec<&Edge> = children
+                .iter()
+                .filter(|c| c.visits() > 0 && !matches!(c.proof(), Proof::Loss(_)))
+                .collect();
 
-            if avg_val > best_value {
-                best_value = avg_val;
-                best_move = child.from_action;
+            if !candidates.is_empty() {
+                let visits: Vec<u32> = candidates.iter().map(|c| c.visits()).collect();
+                let mut rng = self.rng.lock().unwrap();
+                let i = noise::sample_by_visits(&mut rng, &visits, self.params.temperature);
+                return candidates[i].mv;
             }
         }
 
-        best_move
+        if let Some(gumbel) = self.gumbel.lock().unwrap().as_ref() {
+            let (visits, q) = root_statistics(children);
+            return children[gumbel.best(&visits, &q)].mv;
+        }
+
+        let policy = match self.params.final_move {
+            // Out of time without agreement, fall back to the robust child
+            FinalMove::MaxValueVisits if !self.final_moves_agree() => FinalMove::MostVisits,
+            policy => policy,
+        };
+
+        // Nothing visited yet, the heuristic order is all there is
+        final_child(children, policy, self.params.secure_c)
+            .or(children.first())
+            .map_or(Move::null(), |child| child.mv)
+    }
+
+    // The final output leads with the move that is played, even if it
+    // isn't the most visited one
+    fn print_info(&self, time: Instant, best_move: Option<Move>) {
+        let nodes = self.root.visits() as u64;
+        let stats = SearchStats {
+            depth: (self.depth_sum.load(Ordering::Relaxed) / nodes.max(1)) as u32,
+            seldepth: self.seldepth.load(Ordering::Relaxed),
+            nodes,
+            time: time.elapsed().as_millis(),
+            hashfull: (self.node_count.load(Ordering::Relaxed) * 1000 / self.capacity) as u32,
+        };
+
+        let mut lines = self.pv_lines(usize::MAX);
+        if let Some(i) = lines.iter().position(|l| Some(l.pv[0]) == best_move) {
+            lines[..=i].rotate_right(1);
+        }
+        lines.truncate(self.params.multipv);
+
+        // A single line speaks for the whole position
+        if let ([line], Some(value)) = (lines.as_mut_slice(), self.root_value()) {
+            let proof = self.root.proof();
+            line.score = Score::new(value, proof, self.params.eval_scale);
+            line.wdl = self
+                .params
+                .show_wdl
+                .then(|| info::wdl(value, self.root.draw_rate(), proof));
+        }
+
+        info::print_info(&stats, &lines);
+    }
+
+    // Visited root moves, proven losses last and the rest by visits, each
+    // followed by the most visited line below it
+    pub fn pv_lines(&self, count: usize) -> Vec<PvLine> {
+        let Some(children) = self.root.children.get() else {
+            return Vec::new();
+        };
+
+        let mut order: Vec<&Edge> = children
+            .iter()
+            .filter(|c| c.visits() > 0 || c.proof().is_solved())
+            .collect();
+        order.sort_by_key(|c| (matches!(c.proof(), Proof::Loss(_)), u32::MAX - c.visits()));
+
+        order
+            .into_iter()
+            .take(count)
+            .map(|child| {
+                let value = match child.visits() {
+                    0 => 0.5,
+                    n => child.total_value.load() / n as f32,
+                };
+                // The root as if this were its only move
+                let proof = Proof::from_children(std::iter::once(child.proof()));
+
+                // Moves below shared nodes are taken back to the root's board
+                let mut pv = vec![child.mv];
+                let mut node: &Node = &child.node;
+                let mut to_root = symmetry::inverse(child.sym);
+                while let Some(next) = node.children.get().and_then(|c| {
+                    c.iter()
+                        .filter(|c| c.visits() > 0)
+                        .max_by_key(|c| c.visits())
+                }) {
+                    pv.push(next.mv.transform(to_root));
+                    node = &next.node;
+                    to_root = symmetry::compose(symmetry::inverse(next.sym), to_root);
+                }
+
+                PvLine {
+                    score: Score::new(value, proof, self.params.eval_scale),
+                    wdl: self
+                        .params
+                        .show_wdl
+                        .then(|| info::wdl(value, child.draw_rate(), proof)),
+                    visits: child.visits(),
+                    pv,
+                }
+            })
+            .collect()
+    }
+
+    // Only MaxValueVisits has anything to agree on
+    fn final_moves_agree(&self) -> bool {
+        if self.params.final_move != FinalMove::MaxValueVisits {
+            return true;
+        }
+
+        let Some(children) = self.root.children.get() else {
+            return false;
+        };
+
+        let most_visits = final_child(children, FinalMove::MostVisits, self.params.secure_c);
+        let max_value = final_child(children, FinalMove::MaxValue, self.params.secure_c);
+        match (most_visits, max_value) {
+            (Some(a), Some(b)) => a.mv == b.mv,
+            _ => false,
+        }
     }
 
-    pub fn confirm_logic(&self) {
-        for node in self.nodes.iter() {
-            let mut child_visits = 0;
-            for child_idx in (*node.children).borrow().iter() {
-                let child = &self.nodes[*child_idx];
-                child_visits += child.visits;
+    #[cfg(test)]
+    fn root_proof(&self) -> Proof {
+        self.root.proof()
+    }
+
+    pub fn exact_score(&self) -> Option<i32> {
+        self.exact_score
+    }
+
+    // Expected score for the side to move, None before any simulation
+    pub fn root_value(&self) -> Option<f32> {
+        // The root's proof and statistics are kept for the side that moved
+        // into it
+        match self.root.proof() {
+            Proof::Win(_) => return Some(0.0),
+            Proof::Loss(_) => return Some(1.0),
+            Proof::Draw(_) => return Some(0.5),
+            _ => (),
+        }
+
+        match self.root.visits() {
+            0 => None,
+            n => Some(1.0 - self.root.total_value.load() / n as f32),
+        }
+    }
+
+    // The node for a position and the symmetry from the position to the
+    // board the node keeps
+    fn node_for(&self, position: Position) -> (Arc<Node>, usize) {
+        let Some(table) = &self.table else {
+            self.node_count.fetch_add(1, Ordering::Relaxed);
+            return (Arc::new(Node::new(position)), 0);
+        };
+
+        // Symmetric positions all share the node of the canonical board
+        let sym = if self.params.symmetry_hash {
+            position.canonical_symmetry()
+        } else {
+            0
+        };
+        let position = position.transform(sym);
+        // The ply counter keeps the graph acyclic, and the 100 ply rule
+        // makes it part of the game state anyway
+        let key = position.hash() ^ (position.half_moves as u64).wrapping_mul(0x9e3779b97f4a7c15);
+
+        let mut shard = table.shards[key as usize % TABLE_SHARDS].lock().unwrap();
+        let node = shard.entry(key).or_insert_with(|| {
+            self.node_count.fetch_add(1, Ordering::Relaxed);
+            Arc::new(Node::new(position))
+        });
+
+        (node.clone(), sym)
+    }
+
+    #[cfg(test)]
+    fn confirm_logic(&self) {
+        // Every node of the graph once, with the visits of all edges into it
+        let mut incoming: HashMap<*const Node, u32> = HashMap::new();
+        let mut nodes = vec![&self.root];
+        let mut next = 0;
+
+        while let Some(&node) = nodes.get(next) {
+            next += 1;
+            assert_eq!(node.virtual_loss.load(Ordering::Relaxed), 0);
+
+            let Some(children) = node.children.get() else {
+                continue;
+            };
+
+            let child_visits: u32 = children.iter().map(|c| c.visits()).sum();
+            // The root is never rolled out itself, every other node is
+            // rolled out once before it gets expanded
+            let own_visit = if std::ptr::eq(node, &self.root) { 0 } else { 1 };
+            assert_eq!(node.visits(), child_visits + own_visit);
+
+            for child in children.iter() {
+                assert!(child.visits() <= child.node.visits());
+                assert!(child.total_value.load() <= child.visits() as f32);
+
+                // The edge knows the board its node is kept on
+                let mut position = node.position;
+                position.make_move(child.mv);
+                assert_eq!(position.transform(child.sym), child.node.position);
+
+                let seen = incoming.contains_key(&Arc::as_ptr(&child.node));
+                *incoming.entry(Arc::as_ptr(&child.node)).or_default() += child.visits();
+                if !seen {
+                    nodes.push(&child.node);
+                }
             }
-            debug_assert_eq!(node.visits, child_visits + 1)
+        }
+
+        // Shared nodes add up the visits of all their parents
+        for node in nodes.iter().skip(1) {
+            assert_eq!(node.visits(), incoming[&std::ptr::from_ref(*node)]);
         }
     }
 }
 
-impl Node {
+impl TranspositionTable {
     fn new() -> Self {
-        Node {
-            idx: 0,
-            parent: None,
-            children: Rc::new(RefCell::new(Vec::new())),
-            visits: 0,
-            total_value: 0.0,
-            position: Position::default(),
-            from_action: Move::null(),
+        TranspositionTable {
+            shards: (0..TABLE_SHARDS)
+                .map(|_| Mutex::new(HashMap::new()))
+                .collect(),
+        }
+    }
+}
+
+impl Edge {
+    fn new(mv: Move, prior: f32, node: Arc<Node>, sym: usize) -> Self {
+        Edge {
+            mv,
+            prior,
+            visits: AtomicU32::new(0),
+            total_value: AtomicF32::new(0.0),
+            draws: AtomicF32::new(0.0),
+            amaf_visits: AtomicU32::new(0),
+            amaf_value: AtomicF32::new(0.0),
+            node,
+            sym,
         }
     }
 
-    fn ucb1(&self, tree: &Tree) -> f32 {
-        if self.visits == 0 {
+    fn visits(&self) -> u32 {
+        self.visits.load(Ordering::Relaxed)
+    }
+
+    // Proofs are exact, so all parents share the node's
+    fn proof(&self) -> Proof {
+        self.node.proof()
+    }
+
+    fn draw_rate(&self) -> f32 {
+        match self.visits() {
+            0 => 0.0,
+            n => self.draws.load() / n as f32,
+        }
+    }
+
+    fn ucb1(&self, parent_visits: u32, params: &SearchParams) -> f32 {
+        let visits = self.visits() + self.node.virtual_loss.load(Ordering::Relaxed);
+        if visits == 0 {
             return INFINITY;
         }
 
-        let exploitation = self.total_value / self.visits as f32;
-        let exploration = C
-            * ((2.0 * (tree.nodes[self.parent.unwrap()].visits as f32).ln()) / self.visits as f32)
-                .sqrt();
+        let exploitation = self.exploitation(visits, params);
+        let exploration = C * ((2.0 * (parent_visits as f32).ln()) / visits as f32).sqrt();
         let reward = exploitation + exploration;
 
         debug_assert!(!reward.is_nan());
@@ -150,130 +896,872 @@ impl Node {
         reward
     }
 
-    fn best_child(&self, tree: &Tree) -> usize {
-        debug_assert!(self.is_expanded());
-        let mut best_value = -INFINITY;
-        let mut best_child = None;
+    fn puct(&self, parent_visits: u32, params: &SearchParams) -> f32 {
+        let visits = self.visits() + self.node.virtual_loss.load(Ordering::Relaxed);
+        // A transposition visited through other parents already has a value
+        let exploitation = if visits == 0 && self.node.visits() == 0 {
+            params.fpu
+        } else {
+            self.exploitation(visits, params)
+        };
 
-        let children = (*self.children).borrow();
+        let exploration =
+            params.cpuct * self.prior * (parent_visits as f32).sqrt() / (1 + visits) as f32;
+        let reward = exploitation + exploration;
 
-        for child_idx in children.iter() {
-            let child = &tree.nodes[*child_idx];
-            let child_value = child.ucb1(tree);
+        debug_assert!(!reward.is_nan());
 
-            if child_value > best_value {
-                best_value = child_value;
-                best_child = Some(child_idx);
-            }
+        reward
+    }
+
+    // Average value of the node over all its parents, while the visits
+    // through this edge weigh RAVE. Both include virtual losses.
+    fn exploitation(&self, visits: u32, params: &SearchParams) -> f32 {
+        let node_visits = self.node.visits() + self.node.virtual_loss.load(Ordering::Relaxed);
+        let mut exploitation = self.node.total_value.load() / node_visits as f32;
+
+        // RAVE: lean on the AMAF value while the node has few real visits,
+        // beta halves once visits reach a third of the equivalence parameter
+        let amaf_visits = self.amaf_visits.load(Ordering::Relaxed);
+        if params.rave && amaf_visits > 0 {
+            let k = params.rave_equivalence;
+            let beta = (k / (3.0 * visits as f32 + k)).sqrt();
+            let amaf = self.amaf_value.load() / amaf_visits as f32;
+            exploitation = (1.0 - beta) * exploitation + beta * amaf;
+        }
+
+        exploitation
+    }
+}
+
+impl Node {
+    fn new(position: Position) -> Self {
+        Node {
+            children: OnceLock::new(),
+            visits: AtomicU32::new(0),
+            virtual_loss: AtomicU32::new(0),
+            rolled_out: AtomicBool::new(false),
+            total_value: AtomicF32::new(0.0),
+            draws: AtomicF32::new(0.0),
+            proof: AtomicProof::new(Proof::from_terminal(&position)),
+            position,
+        }
+    }
+
+    fn visits(&self) -> u32 {
+        self.visits.load(Ordering::Relaxed)
+    }
+
+    fn proof(&self) -> Proof {
+        self.proof.load()
+    }
+
+    // Returns whether the node is solved after looking at its children
+    fn update_proof(&self, children: &[Edge]) -> bool {
+        // A finished game keeps its own result whatever moves are left
+        if self.position.game_over() {
+            return true;
+        }
+
+        let proof = Proof::from_children(children.iter().map(|c| c.proof()));
+        if proof.is_solved() {
+            self.proof.store(proof);
         }
 
-        if best_child.is_none() {
-            println!("No best child found");
-            for child_idx in children.iter() {
-                let child = &tree.nodes[*child_idx];
-                println!(
-                    "Child: {} | Visits: {} | Value: {}",
-                    child.from_action,
-                    child.visits,
-                    child.ucb1(tree)
-                );
+        proof.is_solved()
+    }
+
+    // Virtual visits count as losses until the real result is backed up,
+    // which steers other threads away from paths already being searched
+    fn add_virtual_loss(&self, amount: u32) {
+        self.virtual_loss.fetch_add(amount, Ordering::Relaxed);
+    }
+
+    // Credits the result to every child whose move the side to move
+    // played later in the simulation, value is from that side's view
+    fn update_amaf(&self, amaf: &AmafMoves, value: f32) {
+        let Some(children) = self.children.get() else {
+            return;
+        };
+
+        for child in children.iter() {
+            if amaf.contains(self.position.turn, child.mv) {
+                child.amaf_visits.fetch_add(1, Ordering::Relaxed);
+                child.amaf_value.fetch_add(value);
             }
         }
-        best_child.copied().unwrap()
     }
 
-    fn rollout(&self) -> f32 {
-        // Random mean rollouts
-        let mut position: Position = self.position;
+    fn best_child<'a>(&self, children: &'a [Edge], params: &SearchParams) -> &'a Edge {
+        debug_assert!(!children.is_empty());
+        let parent_visits = self.visits() + self.virtual_loss.load(Ordering::Relaxed);
+        let mut best_value = -INFINITY;
+        let mut best_child = &children[0];
+
+        // Solved children need no more visits, their value is exact
+        let candidates = children
+            .iter()
+            .filter(|c| !c.proof().is_solved())
+            .take(widening_limit(parent_visits, params));
+
+        for child in candidates {
+            let child_value = match params.selection {
+                Selection::Ucb1 => child.ucb1(parent_visits, params),
+                Selection::Puct => child.puct(parent_visits, params),
+            };
 
-        while !position.game_over() {
-            let moves = position.generate_moves();
-            let random_move = moves.data[fastrand::usize(..moves.len())];
-            position.make_move(random_move);
+            if child_value > best_value {
+                best_value = child_value;
+                best_child = child;
+            }
         }
 
-        match position.winner().unwrap() {
-            Outcome::WhiteWin => 1.0,
-            Outcome::BlackWin => 0.0,
-            Outcome::Draw => 0.5,
+        best_child
+    }
+
+    fn draw_rate(&self) -> f32 {
+        match self.visits() {
+            0 => 0.0,
+            n => self.draws.load() / n as f32,
         }
     }
 
-    fn mean_rollout(&self) -> f32 {
+    fn rollout(&self, params: &SearchParams, amaf: &mut AmafMoves, rng: &mut Rng) -> Playout {
+        rollout::playout(self.position, params, amaf, rng)
+    }
+
+    // Mean value for the side to move and the share of drawn playouts
+    fn mean_rollout(
+        &self,
+        params: &SearchParams,
+        amaf: &mut AmafMoves,
+        rng: &mut Rng,
+    ) -> (f32, f32) {
         let mut sum = 0.0;
-        let repetitions = 10;
+        let mut draws = 0;
+        // A zero ply cutoff evaluates the leaf itself, once is enough
+        let repetitions = if params.truncated && params.rollout_depth == 0 {
+            1
+        } else {
+            params.playouts
+        };
 
         for _ in 0..repetitions {
-            sum += self.rollout();
+            let playout = self.rollout(params, amaf, rng);
+            sum += playout.value;
+            draws += playout.draw as u32;
         }
 
+        let draws = draws as f32 / repetitions as f32;
         if self.position.turn == Side::White {
-            sum / repetitions as f32
+            (sum / repetitions as f32, draws)
         } else {
-            1.0 - (sum / repetitions as f32)
+            (1.0 - (sum / repetitions as f32), draws)
         }
     }
 
-    fn default_policy(&self) -> f32 {
-        1.0 - self.mean_rollout()
+    // Value for the side that moved into the node, and the draw share
+    fn default_policy(
+        &self,
+        params: &SearchParams,
+        amaf: &mut AmafMoves,
+        rng: &mut Rng,
+    ) -> (f32, f32) {
+        let (value, draws) = self.mean_rollout(params, amaf, rng);
+        (1.0 - value, draws)
     }
 
-    fn expand(&self, tree: &mut Tree) -> Node {
-        debug_assert!(self.is_expandable());
-        debug_assert!(!self.is_terminal());
+    fn expand(&self, tree: &Tree) -> Option<&[Edge]> {
+        if let Some(children) = self.children.get() {
+            return Some(children);
+        }
 
-        let idx = self.children.borrow().len();
-        let mut new_pos = self.position;
-        let mv = new_pos.generate_moves().data[idx];
-        new_pos.make_move(mv);
+        if tree.node_count.load(Ordering::Relaxed) >= tree.capacity {
+            return None;
+        }
 
-        let new_node = Node {
-            idx: tree.nodes.len(),
-            parent: Some(self.idx),
-            children: Rc::new(RefCell::new(Vec::new())),
-            visits: 0,
-            total_value: 0.0,
-            position: new_pos,
-            from_action: mv,
-        };
+        let children = self.children.get_or_init(|| {
+            let mut moves = self.position.generate_moves();
+            if std::ptr::eq(self, &tree.root) && !tree.search_moves.is_empty() {
+                moves.retain(|mv| tree.search_moves.contains(mv));
+            }
+            if tree.params.ordered_expansion {
+                policy::order_moves(&self.position, moves.as_mut_slice());
+            }
 
-        debug_assert_eq!(tree.nodes[new_node.parent.unwrap()], tree.nodes[self.idx]);
-        debug_assert_eq!(
-            new_node.from_action,
-            self.position.generate_moves().data[idx]
-        );
-        debug_assert_eq!(new_node.visits, 0);
-        debug_assert_eq!(new_node.total_value, 0.0);
-        debug_assert_eq!(new_node.children.borrow().len(), 0);
+            let priors = policy::priors(&self.position, moves.as_slice(), tree.params.prior);
 
-        (*self.children).borrow_mut().push(new_node.idx);
-        tree.nodes.push(new_node.clone());
-        debug_assert!(self.is_expanded());
+            let children: Box<[Edge]> = moves
+                .as_slice()
+                .iter()
+                .zip(priors)
+                .map(|(mv, prior)| {
+                    let mut new_pos = self.position;
+                    new_pos.make_move(*mv);
+                    let (node, sym) = tree.node_for(new_pos);
+                    Edge::new(*mv, prior, node, sym)
+                })
+                .collect();
 
-        new_node
-    }
+            // Terminal children are solved on creation
+            self.update_proof(&children);
+            children
+        });
+
+        debug_assert!(!children.is_empty());
 
-    fn is_expandable(&self) -> bool {
-        self.children.borrow().len() < self.position.generate_moves().len()
+        Some(children)
     }
+}
 
-    fn is_terminal(&self) -> bool {
-        !self.is_expanded() && !self.is_expandable()
+// A fixed seed still gives every position its own stream, so noise and
+// temperature don't repeat the same draws move after move
+pub fn search_rng(seed: u64, pos: &Position) -> Rng {
+    match seed {
+        0 => Rng::new(),
+        seed => Rng::with_seed(seed ^ pos.hash() ^ pos.half_moves as u64),
     }
+}
+
+// Visited root child chosen by the final move policy, proven losses are
+// never played
+fn final_child(children: &[Edge], policy: FinalMove, secure_c: f32) -> Option<&Edge> {
+    let score = |child: &Edge| {
+        let visits = child.visits() as f32;
+        let value = child.total_value.load() / visits;
+        match policy {
+            FinalMove::MostVisits => visits,
+            FinalMove::MaxValue | FinalMove::MaxValueVisits => value,
+            FinalMove::Secure => value - secure_c / visits.sqrt(),
+        }
+    };
+
+    children
+        .iter()
+        .filter(|c| c.visits() > 0 && !matches!(c.proof(), Proof::Loss(_)))
+        .max_by(|a, b| score(a).total_cmp(&score(b)))
+}
+
+// Visits, including virtual ones, and mean values of the root children
+fn root_statistics(children: &[Edge]) -> (Vec<u32>, Vec<f32>) {
+    children
+        .iter()
+        .map(|c| {
+            let visits = c.visits() + c.node.virtual_loss.load(Ordering::Relaxed);
+            let q = match c.visits() {
+                0 => 0.0,
+                n => c.total_value.load() / n as f32,
+            };
+            (visits, q)
+        })
+        .unzip()
+}
 
-    fn is_expanded(&self) -> bool {
-        !self.children.borrow().is_empty()
+// Number of children open for selection after the given visits
+fn widening_limit(visits: u32, params: &SearchParams) -> usize {
+    if !params.widening {
+        return usize::MAX;
     }
+
+    let limit = params.widening_c * (visits as f32).powf(params.widening_alpha);
+    (limit.ceil() as usize).max(1)
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use crate::engine::params::RolloutPolicy;
 
     #[test]
     fn sanity() {
-        use super::*;
         let mut tree = Tree::new();
-        tree.uct(Position::default(), 5000);
-        assert!(!tree.nodes.is_empty());
+        tree.uct(Position::default(), SearchLimits::from_time(5000));
+        assert!(tree.root.visits() > 0);
+        assert!(tree.node_count.load(Ordering::Relaxed) > 1);
+        tree.confirm_logic();
+    }
+
+    #[test]
+    fn tree_parallel() {
+        let params = SearchParams {
+            threads: 4,
+            ..SearchParams::default()
+        };
+        let mut tree = Tree::with_params(params);
+        let mv = tree.uct(Position::default(), SearchLimits::from_time(500));
+
+        assert_ne!(mv, Move::null());
+        tree.confirm_logic();
+    }
+
+    #[test]
+    fn root_parallel() {
+        let params = SearchParams {
+            threads: 3,
+            parallel: ParallelMode::Root,
+            ..SearchParams::default()
+        };
+        let mut tree = Tree::with_params(params);
+        let mv = tree.uct(Position::default(), SearchLimits::from_time(500));
+        assert_ne!(mv, Move::null());
+
+        let children = tree.root.children.get().unwrap();
+        let child_visits: u32 = children.iter().map(|c| c.visits()).sum();
+        assert_eq!(tree.root.visits(), child_visits);
+
+        // Depths and the lines below the root moves come from the trees
+        assert!(tree.depth_sum.load(Ordering::Relaxed) >= tree.root.visits() as u64);
+        assert!(tree.seldepth.load(Ordering::Relaxed) > 1);
+        let lines = tree.pv_lines(1);
+        assert!(lines[0].pv.len() > 1);
+        assert_eq!(
+            lines[0].visits,
+            children.iter().map(|c| c.visits()).max().unwrap()
+        );
+    }
+
+    #[test]
+    fn rave() {
+        let params = SearchParams {
+            rave: true,
+            ..SearchParams::default()
+        };
+        let mut tree = Tree::with_params(params);
+        let mv = tree.uct(Position::default(), SearchLimits::from_time(500));
+        assert_ne!(mv, Move::null());
+
+        // Every simulation plays a move from the root, each one updates
+        // the AMAF statistics of at least that child
+        let children = tree.root.children.get().unwrap();
+        let amaf_visits: u32 = children
+            .iter()
+            .map(|c| c.amaf_visits.load(Ordering::Relaxed))
+            .sum();
+        assert!(amaf_visits >= tree.root.visits());
+        tree.confirm_logic();
+    }
+
+    #[test]
+    fn puct() {
+        let params = SearchParams {
+            selection: Selection::Puct,
+            ..SearchParams::default()
+        };
+        let mut tree = Tree::with_params(params);
+        let mv = tree.uct(Position::default(), SearchLimits::from_time(500));
+        assert_ne!(mv, Move::null());
+        tree.confirm_logic();
+    }
+
+    #[test]
+    fn puct_follows_prior() {
+        let params = SearchParams {
+            selection: Selection::Puct,
+            ..SearchParams::default()
+        };
+        let tree = Tree::with_params(params);
+        let root = Node::new(Position::from_fen("6o/7/7/7/7/oo5/1x5 x 0 1").unwrap());
+
+        // The capture with the highest prior is tried first
+        root.add_virtual_loss(1);
+        let children = root.expand(&tree).unwrap();
+        let first = root.best_child(children, &tree.params);
+        assert_eq!(first.mv, Move::new(0, 0));
+    }
+
+    #[test]
+    fn progressive_widening() {
+        let params = SearchParams {
+            widening: true,
+            widening_c: 1.0,
+            ..SearchParams::default()
+        };
+        assert_eq!(widening_limit(0, &params), 1);
+        assert_eq!(widening_limit(1, &params), 1);
+        assert_eq!(widening_limit(5, &params), 3);
+        assert_eq!(widening_limit(100, &params), 10);
+
+        let mut tree = Tree::with_params(params);
+        tree.uct(Position::default(), SearchLimits::from_time(300));
+        tree.confirm_logic();
+
+        // Only the first ceil(sqrt(n)) root children ever got a visit
+        let children = tree.root.children.get().unwrap();
+        let visited = children.iter().filter(|c| c.visits() > 0).count();
+        assert!(visited <= widening_limit(tree.root.visits(), &tree.params));
+        assert!(children.iter().skip(visited).all(|c| c.visits() == 0));
+    }
+
+    #[test]
+    fn gumbel_root() {
+        let params = SearchParams {
+            root_selection: RootSelection::Gumbel,
+            gumbel_k: 8,
+            gumbel_simulations: 96,
+            ..SearchParams::default()
+        };
+        let mut tree = Tree::with_params(params);
+        let mv = tree.uct(Position::default(), SearchLimits::from_time(5000));
+        assert_ne!(mv, Move::null());
+
+        // Search stops after the budget, long before the time runs out
+        assert_eq!(tree.root.visits(), 96);
+        let children = tree.root.children.get().unwrap();
+        assert_eq!(children.iter().filter(|c| c.visits() > 0).count(), 8);
+        tree.confirm_logic();
+    }
+
+    #[test]
+    fn transpositions_share_nodes() {
+        let params = SearchParams {
+            graph: true,
+            ..SearchParams::default()
+        };
+        let mut tree = Tree::with_params(params);
+        tree.reset(Position::default());
+
+        // b6 b2 f2 and f2 b2 b6 reach the same position
+        let (b6, b2, f2) = (Move::new(36, 36), Move::new(8, 8), Move::new(12, 12));
+        let mut first = Position::default();
+        first.make_move(b6);
+        first.make_move(b2);
+        let mut second = Position::default();
+        second.make_move(f2);
+        second.make_move(b2);
+
+        let first = Node::new(first);
+        let second = Node::new(second);
+        let a = first
+            .expand(&tree)
+            .unwrap()
+            .iter()
+            .find(|e| e.mv == f2)
+            .unwrap();
+        let b = second
+            .expand(&tree)
+            .unwrap()
+            .iter()
+            .find(|e| e.mv == b6)
+            .unwrap();
+        assert!(Arc::ptr_eq(&a.node, &b.node));
+    }
+
+    #[test]
+    fn graph_search() {
+        for symmetry_hash in [false, true] {
+            let params = SearchParams {
+                graph: true,
+                symmetry_hash,
+                threads: 2,
+                ..SearchParams::default()
+            };
+            let mut tree = Tree::with_params(params);
+            let mv = tree.uct(Position::default(), SearchLimits::from_time(500));
+            assert_ne!(mv, Move::null());
+
+            let table = tree.table.as_ref().unwrap();
+            let nodes: Vec<Arc<Node>> = table
+                .shards
+                .iter()
+                .flat_map(|s| s.lock().unwrap().values().cloned().collect::<Vec<_>>())
+                .collect();
+            assert_eq!(nodes.len() + 1, tree.node_count.load(Ordering::Relaxed));
+            assert!(nodes
+                .iter()
+                .all(|n| n.virtual_loss.load(Ordering::Relaxed) == 0));
+
+            // Mirrored root moves share a node under symmetry hashing
+            let mut children: Vec<&Arc<Node>> = tree
+                .root
+                .children
+                .get()
+                .unwrap()
+                .iter()
+                .map(|c| &c.node)
+                .collect();
+            children.sort_by_key(|c| Arc::as_ptr(c));
+            children.dedup_by(|a, b| Arc::ptr_eq(a, b));
+            let child_visits: u32 = children.iter().map(|c| c.visits()).sum();
+            assert_eq!(tree.root.visits(), child_visits);
+
+            tree.confirm_logic();
+
+            // Lines through shared nodes replay from the root
+            for line in tree.pv_lines(usize::MAX) {
+                let mut pos = Position::default();
+                for mv in line.pv {
+                    assert!(pos.generate_moves().as_slice().contains(&mv), "{}", mv);
+                    pos.make_move(mv);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn final_move_policies() {
+        let mut tree = Tree::new();
+        tree.reset(Position::default());
+        let children = tree.root.expand(&tree).unwrap();
+
+        let set = |child: &Edge, visits: u32, value: f32| {
+            child.visits.store(visits, Ordering::Relaxed);
+            child
+                .total_value
+                .fetch_add(value - child.total_value.load());
+        };
+        set(&children[0], 100, 55.0);
+        set(&children[1], 3, 2.7);
+        set(&children[2], 50, 35.0);
+
+        let pick = |policy| final_child(children, policy, 1.0).unwrap().mv;
+        assert_eq!(pick(FinalMove::MostVisits), children[0].mv);
+        assert_eq!(pick(FinalMove::MaxValue), children[1].mv);
+        assert_eq!(pick(FinalMove::Secure), children[2].mv);
+
+        tree.params.final_move = FinalMove::MaxValueVisits;
+        assert!(!tree.final_moves_agree());
+        assert_eq!(tree.best_move(), children[0].mv);
+
+        set(&children[2], 200, 190.0);
+        assert!(tree.final_moves_agree());
+        assert_eq!(tree.best_move(), children[2].mv);
+    }
+
+    #[test]
+    fn seeded_root_noise() {
+        let params = SearchParams {
+            selection: Selection::Puct,
+            root_noise: true,
+            seed: 7,
+            ..SearchParams::default()
+        };
+        let priors = |params: &SearchParams| {
+            let mut tree = Tree::with_params(params.clone());
+            tree.reset(Position::default());
+            let children = tree.root.expand(&tree).unwrap();
+            children.iter().map(|c| c.prior).collect::<Vec<_>>()
+        };
+
+        let noisy = priors(&params);
+        assert_eq!(noisy, priors(&params));
+        assert_ne!(noisy, priors(&SearchParams::default()));
+        assert!((noisy.iter().sum::<f32>() - 1.0).abs() < 1e-4);
+
+        // UCB1 doesn't use priors, the noise is ignored
+        let ucb1 = SearchParams {
+            selection: Selection::Ucb1,
+            ..params
+        };
+        assert_eq!(priors(&ucb1), priors(&SearchParams::default()));
+    }
+
+    #[test]
+    fn gumbel_root_noise() {
+        // Almost all of the noise lands on a few moves, and the noisy
+        // logits make one of them the only candidate
+        for seed in 1..6 {
+            let params = SearchParams {
+                root_selection: RootSelection::Gumbel,
+                gumbel_k: 1,
+                root_noise: true,
+                dirichlet_alpha: 0.03,
+                dirichlet_epsilon: 1.0,
+                seed,
+                ..SearchParams::default()
+            };
+            let mut tree = Tree::with_params(params);
+            tree.uct(Position::default(), SearchLimits::from_nodes(20));
+
+            let children = tree.root.children.get().unwrap();
+            let searched = children.iter().find(|c| c.visits() > 0).unwrap();
+            assert_eq!(searched.visits(), 20);
+            assert!(searched.prior > 0.1);
+        }
+    }
+
+    #[test]
+    fn temperature_sampling() {
+        let params = SearchParams {
+            temperature_plies: 10,
+            ..SearchParams::default()
+        };
+        let mut tree = Tree::with_params(params);
+        tree.reset(Position::default());
+        let children = tree.root.expand(&tree).unwrap();
+        children[0].visits.store(60, Ordering::Relaxed);
+        children[1].visits.store(40, Ordering::Relaxed);
+
+        let mut played = [0; 2];
+        for _ in 0..200 {
+            let mv = tree.best_move();
+            assert!(mv == children[0].mv || mv == children[1].mv);
+            played[(mv == children[1].mv) as usize] += 1;
+        }
+        assert!(played[0] > 0 && played[1] > 0);
+    }
+
+    #[test]
+    fn seeded_search_is_reproducible() {
+        let params = SearchParams {
+            seed: 1234,
+            rollout: RolloutPolicy::EpsilonGreedy,
+            root_noise: true,
+            ..SearchParams::default()
+        };
+
+        // Visits and values of the whole tree in depth first order
+        fn fingerprint(node: &Node, out: &mut Vec<(u32, u32)>) {
+            out.push((node.visits(), node.total_value.load().to_bits()));
+            for child in node.children.get().into_iter().flatten() {
+                fingerprint(&child.node, out);
+            }
+        }
+
+        let search = || {
+            let mut tree = Tree::with_params(params.clone());
+            let mv = tree.uct(Position::default(), SearchLimits::from_nodes(500));
+            let mut tree_print = Vec::new();
+            fingerprint(&tree.root, &mut tree_print);
+            (mv, tree.node_count.load(Ordering::Relaxed), tree_print)
+        };
+
+        let first = search();
+        assert_eq!(first.2[0].0, 500);
+        assert_eq!(first, search());
+    }
+
+    #[test]
+    fn endgame_solver_takes_over() {
+        let pos =
+            Position::from_fen("-------/-------/-------/-------/-------/-------/ooooxx1 x 0 1")
+                .unwrap();
+        let mut tree = Tree::new();
+        let mv = tree.uct(pos, SearchLimits::from_time(5000));
+
+        assert_eq!(mv, Move::new(6, 6));
+        assert_eq!(tree.exact_score(), Some(-1));
+        assert_eq!(tree.root.visits(), 0);
+    }
+
+    #[test]
+    fn pv_lines() {
+        let mut tree = Tree::with_params(SearchParams {
+            seed: 3,
+            ..SearchParams::default()
+        });
+        let mv = tree.uct(Position::default(), SearchLimits::from_nodes(300));
+
+        let lines = tree.pv_lines(3);
+        assert_eq!(lines.len(), 3);
+        assert!(lines.windows(2).all(|w| w[0].visits >= w[1].visits));
+        let best = lines.iter().find(|l| l.pv[0] == mv).unwrap();
+        assert_eq!(best.visits, lines[0].visits);
+
+        for line in lines.iter() {
+            let mut pos = Position::default();
+            for mv in line.pv.iter() {
+                assert!(pos.generate_moves().as_slice().contains(mv));
+                pos.make_move(*mv);
+            }
+        }
+
+        assert_eq!(tree.pv_lines(1000).len(), 16);
+        assert!(tree.seldepth.load(Ordering::Relaxed) >= 2);
+        assert!(tree.depth_sum.load(Ordering::Relaxed) >= 300);
+    }
+
+    #[test]
+    fn draw_statistics() {
+        // Close to the 100 ply rule most playouts end level
+        let pos = Position::from_fen("x5o/7/7/7/7/7/o5x x 96 49").unwrap();
+        let mut tree = Tree::with_params(SearchParams {
+            seed: 5,
+            ..SearchParams::default()
+        });
+        tree.uct(pos, SearchLimits::from_nodes(200));
+
+        let draw_rate = tree.root.draw_rate();
+        assert!(0.0 < draw_rate && draw_rate <= 1.0);
+        let [w, d, l] = info::wdl(tree.root_value().unwrap(), draw_rate, tree.root.proof());
+        assert_eq!(w + d + l, 1000);
+        assert!(d > 0);
+    }
+
+    #[test]
+    fn search_moves() {
+        let search_moves = vec![Move::new(12, 12), Move::new(35, 35)];
+        let limits = SearchLimits {
+            search_moves: search_moves.clone(),
+            ..SearchLimits::from_nodes(200)
+        };
+        let mut tree = Tree::new();
+        let mv = tree.uct(Position::default(), limits.clone());
+        assert!(search_moves.contains(&mv));
+        assert_eq!(tree.root.children.get().unwrap().len(), 2);
+
+        let params = SearchParams {
+            threads: 2,
+            parallel: ParallelMode::Root,
+            ..SearchParams::default()
+        };
+        let mv = Tree::with_params(params).uct(Position::default(), limits);
+        assert!(search_moves.contains(&mv));
+
+        // A single allowed move needs no search
+        let limits = SearchLimits {
+            search_moves: vec![Move::new(6, 20), Move::new(24, 24)],
+            ..SearchLimits::default()
+        };
+        assert_eq!(
+            Tree::new().uct(Position::default(), limits),
+            Move::new(6, 20)
+        );
+    }
+
+    #[test]
+    fn depth_limit() {
+        let limits = SearchLimits {
+            depth: Some(2),
+            ..SearchLimits::default()
+        };
+        let mut tree = Tree::new();
+        tree.uct(Position::default(), limits);
+
+        let visits = tree.root.visits() as u64;
+        assert!(visits > 0 && visits < 10_000);
+        assert!(tree.depth_sum.load(Ordering::Relaxed) / visits >= 2);
+    }
+
+    #[test]
+    fn mate_check_keeps_to_time() {
+        let params = SearchParams {
+            mate_check: true,
+            mate_check_moves: 10,
+            pns_memory: 1024,
+            ..SearchParams::default()
+        };
+        let time = Instant::now();
+        let mv = Tree::with_params(params.clone())
+            .uct(Position::default(), SearchLimits::from_time(100));
+
+        // The move time is a hard limit, the check gets nothing of it
+        assert!(time.elapsed() < Duration::from_millis(150));
+        assert_ne!(mv, Move::null());
+
+        // A share of the clock may be extended, the check uses the rest
+        let limits = SearchLimits {
+            time_left: Some(2050),
+            moves_to_go: Some(20),
+            ..SearchLimits::default()
+        };
+        let time = Instant::now();
+        Tree::with_params(params).uct(Position::default(), limits);
+        assert!(time.elapsed() < Duration::from_millis(250));
+    }
+
+    #[test]
+    fn stop_signal() {
+        let params = SearchParams {
+            threads: 2,
+            ..SearchParams::default()
+        };
+        let mut tree = Tree::with_params(params);
+        let stop = tree.stop_signal();
+
+        let time = Instant::now();
+        let mv = std::thread::scope(|s| {
+            let search = s.spawn(|| tree.uct(Position::default(), SearchLimits::default()));
+            std::thread::sleep(Duration::from_millis(100));
+            stop.store(true, Ordering::Relaxed);
+            search.join().unwrap()
+        });
+
+        assert!(time.elapsed() < Duration::from_secs(5));
+        assert!(Position::default()
+            .generate_moves()
+            .as_slice()
+            .contains(&mv));
+        assert!(tree.root.visits() > 0);
+    }
+
+    #[test]
+    fn endgame_solver_leaves_time() {
+        // Too many empty squares to solve in a few milliseconds
+        let pos =
+            Position::from_fen("xxxxooo/ooxxxoo/x1o1x1o/xxooxxo/oo1xx1o/xxoooxx/1xoxo1x x 0 1")
+                .unwrap();
+        let time = Instant::now();
+        let mut tree = Tree::new();
+        tree.uct(pos, SearchLimits::from_time(10));
+
+        assert!(time.elapsed() < Duration::from_millis(200));
+        assert_eq!(tree.exact_score(), None);
+        assert!(tree.root.visits() > 0);
+    }
+
+    #[test]
+    fn game_over_without_moves() {
+        let pos = Position::from_fen("7/7/7/7/7/7/x6 o 0 1").unwrap();
+        let mv = Tree::new().uct(pos, SearchLimits::from_nodes(100));
+        assert_eq!(mv.to_string(), "0000");
+
+        // Moves are still generated at the move limit
+        let pos = Position::from_fen("x5o/7/7/7/7/7/o5x x 100 51").unwrap();
+        let mut tree = Tree::new();
+        assert_eq!(
+            tree.uct(pos, SearchLimits::from_nodes(100)).to_string(),
+            "0000"
+        );
+        assert_eq!(tree.exact_score(), None);
+
+        let node = Node::new(pos);
+        assert!(node.expand(&tree).is_some());
+        assert_eq!(node.proof(), Proof::Draw(0));
+    }
+
+    #[test]
+    fn move_without_search_time() {
+        let mut tree = Tree::new();
+        let mv = tree.uct(Position::default(), SearchLimits::from_time(0));
+        assert!(Position::default()
+            .generate_moves()
+            .as_slice()
+            .contains(&mv));
+    }
+
+    #[test]
+    fn solver_proves_win() {
+        // b1 captures the last white stone, a2 loses everything
+        let pos =
+            Position::from_fen("-------/-------/-------/-------/-------/1------/x1o---- x 0 1")
+                .unwrap();
+        let mut tree = Tree::with_params(SearchParams {
+            endgame_empties: 0,
+            ..SearchParams::default()
+        });
+        let time = Instant::now();
+        let mv = tree.uct(pos, SearchLimits::from_time(5000));
+
+        assert_eq!(mv, Move::new(1, 1));
+        assert_eq!(tree.root_proof(), Proof::Loss(1));
+        assert_eq!(tree.root_proof().mate_score(), Some(1));
+        assert_eq!(tree.root_value(), Some(1.0));
+        assert!(time.elapsed().as_millis() < 5000);
+    }
+
+    #[test]
+    fn solver_proves_loss() {
+        // g1 fills the board with white ahead, e1g1 lets white recapture on e1
+        let pos =
+            Position::from_fen("-------/-------/-------/-------/-------/-------/ooooxx1 x 0 1")
+                .unwrap();
+        let mut tree = Tree::with_params(SearchParams {
+            endgame_empties: 0,
+            ..SearchParams::default()
+        });
+        tree.uct(pos, SearchLimits::from_time(5000));
+
+        assert_eq!(tree.root_proof(), Proof::Win(2));
+        assert_eq!(tree.root_proof().mate_score(), Some(-1));
     }
 }
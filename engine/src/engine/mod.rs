@@ -1,4 +1,19 @@
+mod atomic;
+pub mod book;
+pub mod endgame;
+mod eval;
+mod gumbel;
+pub mod info;
+pub mod limits;
 pub mod mcts;
 pub mod moves;
+mod noise;
+pub mod params;
 pub mod perft;
+pub mod pns;
+mod policy;
+pub mod proof;
+mod rave;
+pub mod retrograde;
+mod rollout;
 mod statvec;
@@ -2,12 +2,19 @@ use super::statvec::StaticVec;
 use crate::ataxx::{
     bitboard::BitBoard,
     position::{Position, Side},
+    symmetry::transform_square,
 };
-use std::fmt::Display;
+use std::{fmt::Display, str::FromStr};
 
 const MAX_MOVES: usize = 256;
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Debug, PartialEq)]
+pub enum MoveError {
+    Notation,
+    Illegal,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Move {
     pub from: u8,
     pub to: u8,
@@ -29,6 +36,18 @@ impl Move {
     const fn is_single(&self) -> bool {
         self.from == self.to
     }
+
+    // The same move on a board transformed by the given symmetry
+    pub fn transform(&self, sym: usize) -> Move {
+        if *self == Move::pass() || *self == Move::null() {
+            return *self;
+        }
+
+        Move::new(
+            transform_square(self.from, sym),
+            transform_square(self.to, sym),
+        )
+    }
 }
 
 // Shamelessely stolen from Rustaxx (kz04px)
@@ -56,7 +75,46 @@ impl Display for Move {
     }
 }
 
+// Same notation as Display: a single as its target square, a jump as both
+// squares, a pass as 0000
+impl FromStr for Move {
+    type Err = MoveError;
+
+    fn from_str(text: &str) -> Result<Move, MoveError> {
+        let square = |text: &[u8]| match text {
+            [file @ b'a'..=b'g', rank @ b'1'..=b'7'] => Ok((rank - b'1') * 7 + (file - b'a')),
+            _ => Err(MoveError::Notation),
+        };
+
+        let text = text.to_ascii_lowercase();
+        match text.as_bytes() {
+            b"0000" => Ok(Move::pass()),
+            [a, b] => {
+                let sq = square(&[*a, *b])?;
+                Ok(Move::new(sq, sq))
+            }
+            [a, b, c, d] => Ok(Move::new(square(&[*a, *b])?, square(&[*c, *d])?)),
+            _ => Err(MoveError::Notation),
+        }
+    }
+}
+
 impl Position {
+    // Singles may also be written with both squares, as in f1f2
+    pub fn parse_move(&self, text: &str) -> Result<Move, MoveError> {
+        let mut mv: Move = text.parse()?;
+        let distance = |a: u8, b: u8| (a % 7).abs_diff(b % 7).max((a / 7).abs_diff(b / 7));
+        if mv != Move::pass() && distance(mv.from, mv.to) == 1 {
+            mv = Move::new(mv.to, mv.to);
+        }
+
+        if !self.generate_moves().as_slice().contains(&mv) {
+            return Err(MoveError::Illegal);
+        }
+
+        Ok(mv)
+    }
+
     pub fn generate_moves(&self) -> StaticVec<Move, MAX_MOVES> {
         let mut moves: StaticVec<Move, MAX_MOVES> = StaticVec::new(Move::null());
         if self.must_pass() {
@@ -106,6 +164,18 @@ impl Position {
         self.update_turn_info();
     }
 
+    // Stones won by the side to move: every capture, plus the new stone of a single
+    pub fn move_gain(&self, mv: Move) -> u32 {
+        if mv == Move::pass() {
+            return 0;
+        }
+
+        let opponent = self.colored_squares(!self.turn);
+        let captures = (BitBoard::from_index(mv.to).singles() & opponent).popcnt();
+
+        captures + mv.is_single() as u32
+    }
+
     fn update_turn_info(&mut self) {
         self.turn = !self.turn;
         self.half_moves += 1;
@@ -156,4 +226,43 @@ mod tests {
         pos.make_move(mv);
         assert_eq!(pos.white, BitBoard(0x1000010204000));
     }
+
+    #[test]
+    fn parse_move() {
+        let pos = Position::from_fen("x5o/7/7/7/7/7/o5x x 0 1").unwrap();
+        for mv in pos.generate_moves().as_slice() {
+            assert_eq!(mv.to_string().parse(), Ok(*mv));
+        }
+
+        assert_eq!("0000".parse(), Ok(Move::pass()));
+        assert_eq!("A7B7".parse(), Ok(Move::new(42, 43)));
+        assert_eq!("h1".parse::<Move>(), Err(MoveError::Notation));
+        assert_eq!("a1a".parse::<Move>(), Err(MoveError::Notation));
+
+        assert_eq!(pos.parse_move("f2"), Ok(Move::new(12, 12)));
+        assert_eq!(pos.parse_move("g1f2"), Ok(Move::new(12, 12)));
+        assert_eq!(pos.parse_move("g1g3"), Ok(Move::new(6, 20)));
+        assert_eq!(pos.parse_move("d4"), Err(MoveError::Illegal));
+        assert_eq!(pos.parse_move("0000"), Err(MoveError::Illegal));
+        assert_eq!(pos.parse_move("x"), Err(MoveError::Notation));
+    }
+
+    #[test]
+    fn transform() {
+        assert_eq!(Move::new(42, 28).transform(2), Move::new(0, 14));
+        assert_eq!(Move::new(12, 12).transform(1), Move::new(8, 8));
+        assert_eq!(Move::pass().transform(3), Move::pass());
+    }
+
+    #[test]
+    fn move_gain() {
+        let pos = Position::from_fen("x5o/7/7/7/7/7/o5x x 0 1").unwrap();
+        assert_eq!(pos.move_gain(Move::new(43, 43)), 1);
+        assert_eq!(pos.move_gain(Move::new(42, 28)), 0);
+        assert_eq!(pos.move_gain(Move::pass()), 0);
+
+        let pos = Position::from_fen("7/7/7/7/7/ooo4/1x5 x 0 1").unwrap();
+        assert_eq!(pos.move_gain(Move::new(0, 0)), 3);
+        assert_eq!(pos.move_gain(Move::new(1, 16)), 2);
+    }
 }
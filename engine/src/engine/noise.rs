@@ -0,0 +1,117 @@
+use fastrand::Rng;
+
+// Random vector on the simplex, every component drawn with concentration alpha
+pub fn dirichlet(rng: &mut Rng, alpha: f32, len: usize) -> Vec<f32> {
+    let mut samples: Vec<f32> = (0..len).map(|_| gamma(rng, alpha)).collect();
+    let sum: f32 = samples.iter().sum();
+
+    // Every sample can underflow for tiny alphas, uniform is the limit then
+    if sum <= 0.0 {
+        return vec![1.0 / len as f32; len];
+    }
+
+    for sample in samples.iter_mut() {
+        *sample /= sum;
+    }
+
+    samples
+}
+
+// Gamma(alpha, 1) by Marsaglia and Tsang, boosted for alpha < 1
+fn gamma(rng: &mut Rng, alpha: f32) -> f32 {
+    if alpha < 1.0 {
+        let u = rng.f32().max(f32::MIN_POSITIVE);
+        return gamma(rng, alpha + 1.0) * u.powf(1.0 / alpha);
+    }
+
+    let d = alpha - 1.0 / 3.0;
+    let c = 1.0 / (9.0 * d).sqrt();
+
+    loop {
+        let x = normal(rng);
+        let v = (1.0 + c * x).powi(3);
+        if v <= 0.0 {
+            continue;
+        }
+
+        let u = rng.f32().max(f32::MIN_POSITIVE);
+        if u.ln() < 0.5 * x * x + d - d * v + d * v.ln() {
+            return d * v;
+        }
+    }
+}
+
+// Standard normal by Box-Muller
+fn normal(rng: &mut Rng) -> f32 {
+    let u = rng.f32().max(f32::MIN_POSITIVE);
+    let v = rng.f32();
+    (-2.0 * u.ln()).sqrt() * (std::f32::consts::TAU * v).cos()
+}
+
+// Index drawn with probability proportional to visits^(1 / temperature)
+pub fn sample_by_visits(rng: &mut Rng, visits: &[u32], temperature: f32) -> usize {
+    let max = *visits.iter().max().unwrap_or(&0) as f32;
+    if max == 0.0 {
+        return rng.usize(..visits.len());
+    }
+
+    // Relative to the most visits so large counts don't overflow
+    let weights: Vec<f32> = visits
+        .iter()
+        .map(|v| (*v as f32 / max).powf(1.0 / temperature))
+        .collect();
+    let sum: f32 = weights.iter().sum();
+
+    let mut target = rng.f32() * sum;
+    for (i, weight) in weights.iter().enumerate() {
+        target -= weight;
+        if target <= 0.0 && *weight > 0.0 {
+            return i;
+        }
+    }
+
+    visits.iter().rposition(|v| *v > 0).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dirichlet_is_on_simplex() {
+        let mut rng = Rng::with_seed(1);
+        for alpha in [0.03, 0.3, 1.0, 10.0] {
+            let noise = dirichlet(&mut rng, alpha, 20);
+            assert_eq!(noise.len(), 20);
+            assert!(noise.iter().all(|n| *n >= 0.0));
+            assert!((noise.iter().sum::<f32>() - 1.0).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn gamma_mean() {
+        let mut rng = Rng::with_seed(2);
+        for alpha in [0.3, 2.5] {
+            let mean = (0..20000).map(|_| gamma(&mut rng, alpha)).sum::<f32>() / 20000.0;
+            assert!((mean - alpha).abs() < 0.05 * alpha.max(1.0), "{mean}");
+        }
+    }
+
+    #[test]
+    fn visit_sampling() {
+        let mut rng = Rng::with_seed(3);
+        let visits = [0, 10, 0, 30];
+        let mut counts = [0; 4];
+        for _ in 0..1000 {
+            counts[sample_by_visits(&mut rng, &visits, 1.0)] += 1;
+        }
+
+        assert_eq!(counts[0] + counts[2], 0);
+        assert!(counts[3] > 2 * counts[1]);
+
+        // Cold temperatures play the most visited move
+        for _ in 0..100 {
+            assert_eq!(sample_by_visits(&mut rng, &visits, 0.01), 3);
+        }
+    }
+}
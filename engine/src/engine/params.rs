@@ -0,0 +1,222 @@
+use crate::ataxx::startpos;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ParallelMode {
+    // All threads work on one shared tree, spread out by virtual loss
+    Tree,
+    // Every thread builds its own tree, root visits are merged at the end
+    Root,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RolloutPolicy {
+    Random,
+    // Always the move that gains the most stones
+    CaptureGreedy,
+    // Greedy, except for a random move with probability epsilon
+    EpsilonGreedy,
+    // Random, weighted by exp(gain / temperature)
+    Softmax,
+}
+
+impl RolloutPolicy {
+    pub const ALL: [RolloutPolicy; 4] = [
+        RolloutPolicy::Random,
+        RolloutPolicy::CaptureGreedy,
+        RolloutPolicy::EpsilonGreedy,
+        RolloutPolicy::Softmax,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RolloutPolicy::Random => "Random",
+            RolloutPolicy::CaptureGreedy => "CaptureGreedy",
+            RolloutPolicy::EpsilonGreedy => "EpsilonGreedy",
+            RolloutPolicy::Softmax => "Softmax",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<RolloutPolicy> {
+        RolloutPolicy::ALL
+            .into_iter()
+            .find(|p| p.name().eq_ignore_ascii_case(name))
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Evaluation {
+    // Plain stone difference
+    Stones,
+    // Stone difference plus single move mobility
+    Static,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Selection {
+    Ucb1,
+    // AlphaZero style, exploration is scaled by the prior of each move
+    Puct,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Prior {
+    Uniform,
+    // Softmax over captures and single vs jump
+    Heuristic,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RootSelection {
+    // Same selection as the rest of the tree
+    Uct,
+    // Sequential halving over Gumbel sampled candidates
+    Gumbel,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FinalMove {
+    // Robust child, the most visited move
+    MostVisits,
+    MaxValue,
+    // Highest lower confidence bound, value - c / sqrt(visits)
+    Secure,
+    // Searches on until the most visited move also has the highest value
+    MaxValueVisits,
+}
+
+#[derive(Clone, Debug)]
+pub struct SearchParams {
+    pub threads: usize,
+    pub parallel: ParallelMode,
+    pub virtual_loss: u32,
+    pub rollout: RolloutPolicy,
+    pub playouts: u32,
+    pub epsilon: f32,
+    pub softmax_temp: f32,
+    // Truncated rollouts stop after rollout_depth plies and score the
+    // static evaluation instead of the game result
+    pub truncated: bool,
+    pub rollout_depth: u32,
+    pub evaluation: Evaluation,
+    pub eval_scale: f32,
+    // Rapid action value estimation, blended into UCB1 with weight
+    // sqrt(k / (3n + k)) for the equivalence parameter k
+    pub rave: bool,
+    pub rave_equivalence: f32,
+    pub selection: Selection,
+    pub prior: Prior,
+    pub cpuct: f32,
+    // Value assumed for unvisited children under PUCT
+    pub fpu: f32,
+    // Children are created in heuristic order instead of generation order
+    pub ordered_expansion: bool,
+    // Progressive widening only considers the first ceil(c * n^alpha)
+    // children of a node with n visits
+    pub widening: bool,
+    pub widening_c: f32,
+    pub widening_alpha: f32,
+    pub root_selection: RootSelection,
+    pub gumbel_k: usize,
+    // Simulations the halving schedule is planned for, search ends after them
+    pub gumbel_simulations: u32,
+    // Monte Carlo graph search, transpositions share one node
+    pub graph: bool,
+    // Symmetric positions count as transpositions too
+    pub symmetry_hash: bool,
+    pub final_move: FinalMove,
+    pub secure_c: f32,
+    // Dirichlet noise mixed into the root priors under PUCT, or into the
+    // logits of a Gumbel root. Plain UCB1 has nothing to mix it into and
+    // the search ignores it there.
+    pub root_noise: bool,
+    pub dirichlet_alpha: f32,
+    pub dirichlet_epsilon: f32,
+    // The first temperature_plies plies of a game are sampled by
+    // visits^(1 / temperature) instead of the final move policy
+    pub temperature: f32,
+    pub temperature_plies: u8,
+    // 0 draws a fresh seed for every search
+    pub seed: u64,
+    // Weight of the final stone margin in playout results, 0 only counts
+    // wins and losses, 1 only the margin
+    pub margin_weight: f32,
+    // Proof-number search for a short forced win after every search,
+    // its move replaces the one MCTS found
+    pub mate_check: bool,
+    pub mate_check_moves: u32,
+    // Proof-number table budget in MB
+    pub pns_memory: usize,
+    // Positions with fewer empty squares are solved exactly by alpha-beta,
+    // MCTS takes over again if the node budget runs out
+    pub endgame_empties: u32,
+    pub endgame_nodes: u64,
+    // Opening book path, empty for none
+    pub book_file: String,
+    // Small board table written by smallsolve, empty for none
+    pub table_file: String,
+    // Catalogue name of the layout position startpos sets up
+    pub start_pos: &'static str,
+    // Root moves reported in info output
+    pub multipv: usize,
+    // Adds win, draw and loss rates to info output
+    pub show_wdl: bool,
+}
+
+impl SearchParams {
+    // Whether root moves are picked with priors that noise can change
+    pub fn root_priors(&self) -> bool {
+        self.selection == Selection::Puct || self.root_selection == RootSelection::Gumbel
+    }
+}
+
+impl Default for SearchParams {
+    fn default() -> Self {
+        SearchParams {
+            threads: 1,
+            parallel: ParallelMode::Tree,
+            virtual_loss: 1,
+            rollout: RolloutPolicy::Random,
+            playouts: 10,
+            epsilon: 0.1,
+            softmax_temp: 1.0,
+            truncated: false,
+            rollout_depth: 20,
+            evaluation: Evaluation::Stones,
+            eval_scale: 5.0,
+            rave: false,
+            rave_equivalence: 1000.0,
+            selection: Selection::Ucb1,
+            prior: Prior::Heuristic,
+            cpuct: 1.5,
+            fpu: 0.5,
+            ordered_expansion: true,
+            widening: false,
+            widening_c: 2.0,
+            widening_alpha: 0.5,
+            root_selection: RootSelection::Uct,
+            gumbel_k: 16,
+            gumbel_simulations: 200,
+            graph: false,
+            symmetry_hash: false,
+            final_move: FinalMove::MostVisits,
+            secure_c: 1.0,
+            root_noise: false,
+            dirichlet_alpha: 0.3,
+            dirichlet_epsilon: 0.25,
+            temperature: 1.0,
+            temperature_plies: 0,
+            seed: 0,
+            margin_weight: 0.0,
+            mate_check: false,
+            mate_check_moves: 3,
+            pns_memory: 16,
+            endgame_empties: 8,
+            endgame_nodes: 2_000_000,
+            book_file: String::new(),
+            table_file: String::new(),
+            start_pos: startpos::STANDARD,
+            multipv: 1,
+            show_wdl: false,
+        }
+    }
+}
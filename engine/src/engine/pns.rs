@@ -0,0 +1,272 @@
+// Depth-first proof-number search (Nagai, 2002) for forced wins of the
+// side to move. Proof and disproof numbers are always seen from the
+// attacker, the side to move at the root.
+
+use super::{limits::Abort, moves::Move};
+use crate::ataxx::position::{Outcome, Position, Side};
+use std::collections::HashMap;
+
+const INFINITY: u32 = u32::MAX / 2;
+// Table entry plus hash map overhead, roughly
+const ENTRY_BYTES: usize = 32;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Numbers {
+    pn: u32,
+    dn: u32,
+}
+
+const PROVEN: Numbers = Numbers {
+    pn: 0,
+    dn: INFINITY,
+};
+const DISPROVEN: Numbers = Numbers {
+    pn: INFINITY,
+    dn: 0,
+};
+
+#[derive(Debug, PartialEq)]
+pub enum MateResult {
+    // Shortest win in full moves and the move that starts it
+    Mate(u32, Move),
+    NoMate,
+    // The memory budget or the time ran out first, or the search was stopped
+    Unknown,
+}
+
+struct ProofSearch<'a> {
+    table: HashMap<u64, Numbers>,
+    capacity: usize,
+    abort: Abort<'a>,
+    attacker: Side,
+    // Positions at this ply count as disproven
+    max_ply: u32,
+    aborted: bool,
+}
+
+// Looks for a win in at most max_moves moves, deepening one move at a
+// time so the first proof found is the shortest
+pub fn find_mate(
+    position: &Position,
+    max_moves: u32,
+    memory_mb: usize,
+    abort: Abort,
+) -> MateResult {
+    let mut search = ProofSearch {
+        table: HashMap::new(),
+        capacity: memory_mb * 1024 * 1024 / ENTRY_BYTES,
+        abort,
+        attacker: position.turn,
+        max_ply: 0,
+        aborted: false,
+    };
+
+    for moves in 1..=max_moves {
+        search.table.clear();
+        search.max_ply = position.half_moves as u32 + 2 * moves - 1;
+
+        let root = search.mid(position, INFINITY, INFINITY);
+        if search.aborted {
+            return MateResult::Unknown;
+        }
+
+        if root.pn == 0 {
+            return MateResult::Mate(moves, search.proof_move(position));
+        }
+
+        // Deeper searches can't find anything once the game always ends
+        if search.max_ply >= 100 {
+            break;
+        }
+    }
+
+    MateResult::NoMate
+}
+
+impl ProofSearch<'_> {
+    fn key(position: &Position) -> u64 {
+        position.hash() ^ (position.half_moves as u64).wrapping_mul(0x9e3779b97f4a7c15)
+    }
+
+    fn terminal(&self, position: &Position) -> Option<Numbers> {
+        if let Some(outcome) = position.winner() {
+            let won = matches!(
+                (outcome, self.attacker),
+                (Outcome::BlackWin, Side::Black) | (Outcome::WhiteWin, Side::White)
+            );
+            return Some(if won { PROVEN } else { DISPROVEN });
+        }
+
+        if position.half_moves as u32 >= self.max_ply {
+            return Some(DISPROVEN);
+        }
+
+        None
+    }
+
+    fn lookup(&self, position: &Position) -> Numbers {
+        self.terminal(position)
+            .or_else(|| self.table.get(&Self::key(position)).copied())
+            .unwrap_or(Numbers { pn: 1, dn: 1 })
+    }
+
+    // Multiple iterative deepening, searches below the node until one of
+    // its numbers reaches the threshold
+    fn mid(&mut self, position: &Position, th_pn: u32, th_dn: u32) -> Numbers {
+        if let Some(numbers) = self.terminal(position) {
+            return numbers;
+        }
+
+        let children: Vec<Position> = position
+            .generate_moves()
+            .as_slice()
+            .iter()
+            .map(|mv| {
+                let mut child = *position;
+                child.make_move(*mv);
+                child
+            })
+            .collect();
+        let or_node = position.turn == self.attacker;
+
+        loop {
+            let numbers: Vec<Numbers> = children.iter().map(|c| self.lookup(c)).collect();
+            let current = combine(&numbers, or_node);
+            self.table.insert(Self::key(position), current);
+
+            if current.pn >= th_pn || current.dn >= th_dn || self.aborted {
+                return current;
+            }
+
+            if self.table.len() >= self.capacity || self.abort.triggered() {
+                self.aborted = true;
+                return current;
+            }
+
+            let (best, second) = select(&numbers, or_node);
+            let child = numbers[best];
+            let (child_pn, child_dn) = if or_node {
+                (th_pn.min(second + 1), th_dn - current.dn + child.dn)
+            } else {
+                (th_pn - current.pn + child.pn, th_dn.min(second + 1))
+            };
+
+            self.mid(&children[best], child_pn, child_dn);
+        }
+    }
+
+    fn proof_move(&self, position: &Position) -> Move {
+        *position
+            .generate_moves()
+            .as_slice()
+            .iter()
+            .find(|mv| {
+                let mut child = *position;
+                child.make_move(**mv);
+                self.lookup(&child).pn == 0
+            })
+            .unwrap()
+    }
+}
+
+// The attacker needs one proven child at its own nodes, and all of them
+// at the defender's nodes
+fn combine(children: &[Numbers], or_node: bool) -> Numbers {
+    let sum = |values: &mut dyn Iterator<Item = u32>| values.fold(0, |a, b| (a + b).min(INFINITY));
+
+    if or_node {
+        Numbers {
+            pn: children.iter().map(|c| c.pn).min().unwrap(),
+            dn: sum(&mut children.iter().map(|c| c.dn)),
+        }
+    } else {
+        Numbers {
+            pn: sum(&mut children.iter().map(|c| c.pn)),
+            dn: children.iter().map(|c| c.dn).min().unwrap(),
+        }
+    }
+}
+
+// Most proving child and the second smallest number it is compared by
+fn select(children: &[Numbers], or_node: bool) -> (usize, u32) {
+    let number = |c: &Numbers| if or_node { c.pn } else { c.dn };
+
+    let mut best = 0;
+    let mut second = INFINITY;
+    for (i, child) in children.iter().enumerate().skip(1) {
+        if number(child) < number(&children[best]) {
+            second = number(&children[best]);
+            best = i;
+        } else {
+            second = second.min(number(child));
+        }
+    }
+
+    (best, second)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        sync::atomic::AtomicBool,
+        time::{Duration, Instant},
+    };
+
+    #[test]
+    fn mate_in_one() {
+        let pos =
+            Position::from_fen("-------/-------/-------/-------/-------/1------/x1o---- x 0 1")
+                .unwrap();
+        assert_eq!(
+            find_mate(&pos, 3, 1, Abort::default()),
+            MateResult::Mate(1, Move::new(1, 1))
+        );
+    }
+
+    #[test]
+    fn no_mate() {
+        // Every black move loses, see the solver tests
+        let pos =
+            Position::from_fen("-------/-------/-------/-------/-------/-------/ooooxx1 x 0 1")
+                .unwrap();
+        assert_eq!(find_mate(&pos, 5, 1, Abort::default()), MateResult::NoMate);
+
+        assert_eq!(
+            find_mate(&Position::default(), 1, 1, Abort::default()),
+            MateResult::NoMate
+        );
+    }
+
+    #[test]
+    fn memory_budget() {
+        assert_eq!(
+            find_mate(&Position::default(), 10, 0, Abort::default()),
+            MateResult::Unknown
+        );
+    }
+
+    #[test]
+    fn aborted() {
+        let stop = AtomicBool::new(true);
+        let abort = Abort {
+            stop: Some(&stop),
+            deadline: None,
+        };
+        assert_eq!(
+            find_mate(&Position::default(), 10, 1024, abort),
+            MateResult::Unknown
+        );
+
+        let time = Instant::now();
+        let abort = Abort {
+            stop: None,
+            deadline: Some(time + Duration::from_millis(50)),
+        };
+        assert_eq!(
+            find_mate(&Position::default(), 10, 1024, abort),
+            MateResult::Unknown
+        );
+        assert!(time.elapsed() < Duration::from_secs(2));
+    }
+}
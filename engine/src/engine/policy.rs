@@ -0,0 +1,92 @@
+use super::{moves::Move, params::Prior};
+use crate::ataxx::position::Position;
+
+// Softmax temperature of the heuristic prior, in stones
+const PRIOR_TEMP: f32 = 1.0;
+
+// Heuristic preference for a move: captured stones count once, and a
+// single is worth one more than a jump as it keeps the origin stone
+pub fn move_logit(position: &Position, mv: Move) -> f32 {
+    position.move_gain(mv) as f32 / PRIOR_TEMP
+}
+
+// Prior probability of every move, summing to one
+pub fn priors(position: &Position, moves: &[Move], kind: Prior) -> Vec<f32> {
+    match kind {
+        Prior::Uniform => vec![1.0 / moves.len() as f32; moves.len()],
+        Prior::Heuristic => {
+            let logits: Vec<f32> = moves.iter().map(|mv| move_logit(position, *mv)).collect();
+            softmax(&logits)
+        }
+    }
+}
+
+// Most captures first, singles before jumps with the same captures.
+// The sort is stable so equal moves keep generation order.
+pub fn order_moves(position: &Position, moves: &mut [Move]) {
+    moves.sort_by_key(|mv| {
+        let single = (mv.from == mv.to) as u32;
+        let captures = position.move_gain(*mv) - single;
+        std::cmp::Reverse((captures, single))
+    });
+}
+
+pub fn softmax(logits: &[f32]) -> Vec<f32> {
+    let max = logits.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let exps: Vec<f32> = logits.iter().map(|l| (l - max).exp()).collect();
+    let sum: f32 = exps.iter().sum();
+
+    exps.iter().map(|e| e / sum).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn priors_sum_to_one() {
+        let pos = Position::from_fen("7/7/7/7/7/oo5/1x5 x 0 1").unwrap();
+        let moves = pos.generate_moves();
+
+        for kind in [Prior::Uniform, Prior::Heuristic] {
+            let priors = priors(&pos, moves.as_slice(), kind);
+            assert_eq!(priors.len(), moves.len());
+            assert!((priors.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn ordering() {
+        let pos = Position::from_fen("6o/7/7/7/7/oo5/1x5 x 0 1").unwrap();
+        let mut moves = pos.generate_moves();
+        order_moves(&pos, moves.as_mut_slice());
+        let moves = moves.as_slice();
+
+        // a1 captures two, jumps to a3 and b3 capture two, c1 captures one
+        assert_eq!(moves[0], Move::new(0, 0));
+        assert_eq!(moves[1].from, 1);
+        assert_eq!(moves[2].from, 1);
+        assert_eq!(moves[3], Move::new(2, 2));
+
+        for pair in moves.windows(2) {
+            let captures = |mv: Move| pos.move_gain(mv) - (mv.from == mv.to) as u32;
+            assert!(captures(pair[0]) >= captures(pair[1]));
+        }
+    }
+
+    #[test]
+    fn captures_are_preferred() {
+        let pos = Position::from_fen("7/7/7/7/7/oo5/1x5 x 0 1").unwrap();
+        let moves = pos.generate_moves();
+        let priors = priors(&pos, moves.as_slice(), Prior::Heuristic);
+
+        let best = moves
+            .as_slice()
+            .iter()
+            .position(|mv| *mv == Move::new(0, 0))
+            .unwrap();
+        for (i, prior) in priors.iter().enumerate() {
+            assert!(*prior <= priors[best], "{}", moves.data[i]);
+        }
+    }
+}
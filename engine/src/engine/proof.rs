@@ -0,0 +1,170 @@
+use crate::ataxx::position::{Outcome, Position, Side};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+// Game theoretic result of a node, seen from the side that moved into it.
+// The ply count is the distance to the end of the game.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Proof {
+    Unknown,
+    Win(u16),
+    Loss(u16),
+    Draw(u16),
+}
+
+impl Proof {
+    pub fn from_terminal(position: &Position) -> Proof {
+        let mover = !position.turn;
+        match position.winner() {
+            None => Proof::Unknown,
+            Some(Outcome::Draw) => Proof::Draw(0),
+            Some(Outcome::BlackWin) if mover == Side::Black => Proof::Win(0),
+            Some(Outcome::WhiteWin) if mover == Side::White => Proof::Win(0),
+            Some(_) => Proof::Loss(0),
+        }
+    }
+
+    // Combines the proofs of all children into the proof of their parent.
+    // Any child won for the side to move refutes the parent, the parent
+    // is only won if every child is lost.
+    pub fn from_children(children: impl Iterator<Item = Proof>) -> Proof {
+        let mut fastest_win: Option<u16> = None;
+        let mut slowest_loss: Option<u16> = None;
+        let mut draw: Option<u16> = None;
+        let mut unknown = false;
+
+        for proof in children {
+            match proof {
+                Proof::Unknown => unknown = true,
+                Proof::Win(plies) => {
+                    fastest_win = Some(fastest_win.map_or(plies, |p| p.min(plies)))
+                }
+                Proof::Loss(plies) => slowest_loss = slowest_loss.max(Some(plies)),
+                Proof::Draw(plies) => draw = draw.max(Some(plies)),
+            }
+        }
+
+        if let Some(plies) = fastest_win {
+            Proof::Loss(plies + 1)
+        } else if unknown {
+            Proof::Unknown
+        } else if let Some(plies) = draw {
+            Proof::Draw(plies + 1)
+        } else if let Some(plies) = slowest_loss {
+            Proof::Win(plies + 1)
+        } else {
+            Proof::Unknown
+        }
+    }
+
+    pub fn is_solved(&self) -> bool {
+        *self != Proof::Unknown
+    }
+
+    // Exact value in the same scale as rollouts
+    pub fn value(&self) -> Option<f32> {
+        match self {
+            Proof::Unknown => None,
+            Proof::Win(_) => Some(1.0),
+            Proof::Loss(_) => Some(0.0),
+            Proof::Draw(_) => Some(0.5),
+        }
+    }
+
+    // Mate-style score in full moves for the side to move at the node,
+    // positive if that side wins
+    pub fn mate_score(&self) -> Option<i32> {
+        match self {
+            Proof::Loss(plies) => Some((*plies as i32 + 1) / 2),
+            Proof::Win(plies) => Some(-(*plies as i32 + 1) / 2),
+            _ => None,
+        }
+    }
+
+    fn to_bits(self) -> u32 {
+        match self {
+            Proof::Unknown => 0,
+            Proof::Win(plies) => (plies as u32) << 2 | 1,
+            Proof::Loss(plies) => (plies as u32) << 2 | 2,
+            Proof::Draw(plies) => (plies as u32) << 2 | 3,
+        }
+    }
+
+    fn from_bits(bits: u32) -> Proof {
+        let plies = (bits >> 2) as u16;
+        match bits & 3 {
+            1 => Proof::Win(plies),
+            2 => Proof::Loss(plies),
+            3 => Proof::Draw(plies),
+            _ => Proof::Unknown,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct AtomicProof(AtomicU32);
+
+impl AtomicProof {
+    pub fn new(proof: Proof) -> Self {
+        AtomicProof(AtomicU32::new(proof.to_bits()))
+    }
+
+    pub fn load(&self) -> Proof {
+        Proof::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    pub fn store(&self, proof: Proof) {
+        self.0.store(proof.to_bits(), Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_terminal() {
+        let pos = Position::from_fen("7/7/7/7/7/7/x6 o 0 1").unwrap();
+        assert_eq!(Proof::from_terminal(&pos), Proof::Win(0));
+
+        let pos = Position::from_fen("7/7/7/7/7/7/x6 x 0 1").unwrap();
+        assert_eq!(Proof::from_terminal(&pos), Proof::Loss(0));
+
+        let pos = Position::from_fen("x5o/7/7/7/7/7/o5x x 100 1").unwrap();
+        assert_eq!(Proof::from_terminal(&pos), Proof::Draw(0));
+
+        let pos = Position::default();
+        assert_eq!(Proof::from_terminal(&pos), Proof::Unknown);
+    }
+
+    #[test]
+    fn from_children() {
+        use Proof::*;
+        let combine = |proofs: &[Proof]| Proof::from_children(proofs.iter().copied());
+
+        assert_eq!(combine(&[Unknown, Win(3), Win(1)]), Loss(2));
+        assert_eq!(combine(&[Loss(0), Loss(4)]), Win(5));
+        assert_eq!(combine(&[Loss(0), Draw(2)]), Draw(3));
+        assert_eq!(combine(&[Loss(0), Unknown]), Unknown);
+        assert_eq!(combine(&[Draw(0), Unknown]), Unknown);
+    }
+
+    #[test]
+    fn bits_roundtrip() {
+        for proof in [
+            Proof::Unknown,
+            Proof::Win(7),
+            Proof::Loss(0),
+            Proof::Draw(99),
+        ] {
+            assert_eq!(Proof::from_bits(proof.to_bits()), proof);
+        }
+    }
+
+    #[test]
+    fn mate_score() {
+        assert_eq!(Proof::Loss(1).mate_score(), Some(1));
+        assert_eq!(Proof::Loss(3).mate_score(), Some(2));
+        assert_eq!(Proof::Win(2).mate_score(), Some(-1));
+        assert_eq!(Proof::Draw(2).mate_score(), None);
+    }
+}
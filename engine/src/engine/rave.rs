@@ -0,0 +1,82 @@
+use super::moves::Move;
+use crate::ataxx::{bitboard::BitBoard, position::Side};
+
+// Moves played by each side during one simulation, for all-moves-as-first
+// statistics. Singles are keyed by their target square, jumps by their
+// target square as well, since the origin matters much less in Ataxx.
+#[derive(Clone, Debug, Default)]
+pub struct AmafMoves {
+    singles: [u64; 2],
+    jumps: [u64; 2],
+}
+
+impl AmafMoves {
+    pub fn record(&mut self, side: Side, mv: Move) {
+        if mv == Move::pass() || mv == Move::null() {
+            return;
+        }
+
+        let bit = 1u64 << mv.to;
+        if mv.from == mv.to {
+            self.singles[side_index(side)] |= bit;
+        } else {
+            self.jumps[side_index(side)] |= bit;
+        }
+    }
+
+    // The same moves on a board transformed by the given symmetry
+    pub fn transform(&self, sym: usize) -> AmafMoves {
+        let transform = |bits: [u64; 2]| bits.map(|b| BitBoard(b).transform(sym).0);
+        AmafMoves {
+            singles: transform(self.singles),
+            jumps: transform(self.jumps),
+        }
+    }
+
+    pub fn contains(&self, side: Side, mv: Move) -> bool {
+        if mv == Move::pass() || mv == Move::null() {
+            return false;
+        }
+
+        let bit = 1u64 << mv.to;
+        if mv.from == mv.to {
+            self.singles[side_index(side)] & bit != 0
+        } else {
+            self.jumps[side_index(side)] & bit != 0
+        }
+    }
+}
+
+fn side_index(side: Side) -> usize {
+    match side {
+        Side::Black => 0,
+        Side::White => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_and_contains() {
+        let mut amaf = AmafMoves::default();
+        amaf.record(Side::Black, Move::new(43, 43));
+        amaf.record(Side::White, Move::new(0, 16));
+        amaf.record(Side::White, Move::pass());
+
+        assert!(amaf.contains(Side::Black, Move::new(43, 43)));
+        assert!(!amaf.contains(Side::White, Move::new(43, 43)));
+        assert!(!amaf.contains(Side::Black, Move::new(41, 43)));
+
+        // Jumps only care about where they land
+        assert!(amaf.contains(Side::White, Move::new(2, 16)));
+        assert!(!amaf.contains(Side::White, Move::new(16, 16)));
+        assert!(!amaf.contains(Side::White, Move::pass()));
+
+        // Mirrored left to right, b7 lands on f7
+        let mirrored = amaf.transform(1);
+        assert!(mirrored.contains(Side::Black, Move::new(47, 47)));
+        assert!(!mirrored.contains(Side::Black, Move::new(43, 43)));
+    }
+}
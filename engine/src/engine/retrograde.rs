@@ -0,0 +1,434 @@
+// Strong solver for small boards. Smaller boards are emulated by gaps on
+// the 7x7 board, every position reachable from the start is enumerated
+// and solved backwards from the finished games.
+//
+// The 100 ply rule is ignored, positions are told apart by the stones and
+// the side to move only. Positions that can be played forever without
+// either side forcing a result are draws.
+
+use super::moves::Move;
+use crate::ataxx::{
+    bitboard::BitBoard,
+    position::{Outcome, Position, Side},
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{self, Read},
+};
+
+// 3^39 * 2 positions still fit into a u64 index
+const MAX_SQUARES: u32 = 39;
+
+// Game theoretic value for the side to move
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Value {
+    Draw = 0,
+    Win = 1,
+    Loss = 2,
+}
+
+// Values of all reachable positions, sorted by position index and packed
+// four to a byte
+pub struct SolvedTable {
+    gaps: BitBoard,
+    keys: Vec<u64>,
+    values: Vec<u8>,
+}
+
+// Standard start position on a size x size board in the a1 corner
+pub fn small_board(size: u8) -> Position {
+    assert!((2..=7).contains(&size));
+
+    let mut gaps = BitBoard(0);
+    for rank in 0..7 {
+        for file in 0..7 {
+            if rank >= size as usize || file >= size as usize {
+                gaps |= BitBoard::from_square(file, rank);
+            }
+        }
+    }
+
+    let top = size as usize - 1;
+    Position {
+        black: BitBoard::from_square(0, top) | BitBoard::from_square(top, 0),
+        white: BitBoard::from_square(top, top) | BitBoard::from_square(0, 0),
+        gaps,
+        turn: Side::Black,
+        half_moves: 0,
+        full_moves: 1,
+    }
+}
+
+impl Value {
+    fn from_bits(bits: u8) -> Value {
+        match bits & 3 {
+            1 => Value::Win,
+            2 => Value::Loss,
+            _ => Value::Draw,
+        }
+    }
+}
+
+impl SolvedTable {
+    pub fn solve(start: &Position) -> SolvedTable {
+        let playable = !start.gaps;
+        assert!(playable.popcnt() <= MAX_SQUARES, "Board too large");
+
+        // Breadth first enumeration, children are stored in one flat list
+        let mut ids: HashMap<u64, u32> = HashMap::new();
+        let mut positions = vec![clear_counters(*start)];
+        let mut child_start = vec![0];
+        let mut children: Vec<u32> = Vec::new();
+        ids.insert(index(&positions[0]), 0);
+
+        let mut next = 0;
+        while next < positions.len() {
+            let position = positions[next];
+            next += 1;
+
+            if !position.game_over() {
+                for mv in position.generate_moves().as_slice().iter() {
+                    let mut child = position;
+                    child.make_move(*mv);
+                    let child = clear_counters(child);
+
+                    let id = *ids.entry(index(&child)).or_insert_with(|| {
+                        positions.push(child);
+                        positions.len() as u32 - 1
+                    });
+                    children.push(id);
+                }
+            }
+
+            child_start.push(children.len());
+        }
+
+        // Parents of every position, with one entry per move
+        let mut parent_start = vec![0; positions.len() + 1];
+        for child in children.iter() {
+            parent_start[*child as usize + 1] += 1;
+        }
+        for i in 0..positions.len() {
+            parent_start[i + 1] += parent_start[i];
+        }
+        let mut fill = parent_start.clone();
+        let mut parents = vec![0u32; children.len()];
+        for parent in 0..positions.len() {
+            for child in children[child_start[parent]..child_start[parent + 1]].iter() {
+                parents[fill[*child as usize]] = parent as u32;
+                fill[*child as usize] += 1;
+            }
+        }
+
+        // Retrograde analysis, a position is lost once all its moves are
+        // counted down and none of them reached a draw
+        let mut values: Vec<Option<Value>> = vec![None; positions.len()];
+        let mut unresolved: Vec<usize> = (0..positions.len())
+            .map(|i| child_start[i + 1] - child_start[i])
+            .collect();
+        let mut has_draw = vec![false; positions.len()];
+        let mut queue = VecDeque::new();
+
+        for (id, position) in positions.iter().enumerate() {
+            if let Some(outcome) = position.winner() {
+                values[id] = Some(terminal_value(outcome, position.turn));
+                queue.push_back(id);
+            }
+        }
+
+        while let Some(id) = queue.pop_front() {
+            let value = values[id].unwrap();
+
+            for parent in parents[parent_start[id]..parent_start[id + 1]].iter() {
+                let parent = *parent as usize;
+                if values[parent].is_some() {
+                    continue;
+                }
+
+                match value {
+                    Value::Loss => values[parent] = Some(Value::Win),
+                    Value::Draw | Value::Win => {
+                        has_draw[parent] |= value == Value::Draw;
+                        unresolved[parent] -= 1;
+                        if unresolved[parent] == 0 {
+                            values[parent] = Some(if has_draw[parent] {
+                                Value::Draw
+                            } else {
+                                Value::Loss
+                            });
+                        }
+                    }
+                }
+
+                if values[parent].is_some() {
+                    queue.push_back(parent);
+                }
+            }
+        }
+
+        let mut entries: Vec<(u64, Value)> = ids
+            .into_iter()
+            .map(|(key, id)| (key, values[id as usize].unwrap_or(Value::Draw)))
+            .collect();
+        entries.sort_unstable_by_key(|(key, _)| *key);
+
+        let mut table = SolvedTable {
+            gaps: start.gaps,
+            keys: entries.iter().map(|(key, _)| *key).collect(),
+            values: vec![0; entries.len().div_ceil(4)],
+        };
+        for (i, (_, value)) in entries.iter().enumerate() {
+            table.values[i / 4] |= (*value as u8) << (2 * (i % 4));
+        }
+
+        table
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    // Number of positions that are won, drawn and lost for the side to move
+    pub fn counts(&self) -> [usize; 3] {
+        let mut counts = [0; 3];
+        for i in 0..self.len() {
+            match self.value_at(i) {
+                Value::Win => counts[0] += 1,
+                Value::Draw => counts[1] += 1,
+                Value::Loss => counts[2] += 1,
+            }
+        }
+
+        counts
+    }
+
+    // The move counters of the position don't matter
+    pub fn value(&self, position: &Position) -> Option<Value> {
+        if position.gaps != self.gaps {
+            return None;
+        }
+
+        let i = self.keys.binary_search(&index(position)).ok()?;
+        Some(self.value_at(i))
+    }
+
+    // A move that keeps the value of the position
+    pub fn best_move(&self, position: &Position) -> Option<Move> {
+        let moves = position.generate_moves();
+        let child_value = |mv: &Move| {
+            let mut child = *position;
+            child.make_move(*mv);
+            self.value(&child)
+        };
+
+        [Value::Loss, Value::Draw, Value::Win]
+            .into_iter()
+            .find_map(|target| {
+                moves
+                    .as_slice()
+                    .iter()
+                    .find(|mv| child_value(mv) == Some(target))
+            })
+            .copied()
+    }
+
+    // Position stored at table entry i
+    pub fn position(&self, i: usize) -> Position {
+        let mut key = self.keys[i];
+        let turn = if key.is_multiple_of(2) {
+            Side::Black
+        } else {
+            Side::White
+        };
+        key /= 2;
+
+        let mut position = Position {
+            black: BitBoard(0),
+            white: BitBoard(0),
+            gaps: self.gaps,
+            turn,
+            half_moves: 0,
+            full_moves: 1,
+        };
+        for sq in !self.gaps {
+            match key % 3 {
+                1 => position.black |= BitBoard::from_index(sq),
+                2 => position.white |= BitBoard::from_index(sq),
+                _ => (),
+            }
+            key /= 3;
+        }
+
+        position
+    }
+
+    fn value_at(&self, i: usize) -> Value {
+        Value::from_bits(self.values[i / 4] >> (2 * (i % 4)))
+    }
+
+    pub fn load(path: &str) -> io::Result<SolvedTable> {
+        let file = std::fs::File::open(path)?;
+        SolvedTable::read(&mut io::BufReader::new(file))
+    }
+
+    // Reads what write wrote
+    pub fn read(reader: &mut impl Read) -> io::Result<SolvedTable> {
+        let mut read_u64 = || {
+            let mut bytes = [0; 8];
+            reader
+                .read_exact(&mut bytes)
+                .map(|_| u64::from_le_bytes(bytes))
+        };
+
+        let gaps = BitBoard(read_u64()?);
+        let len = read_u64()? as usize;
+        let keys = (0..len)
+            .map(|_| read_u64())
+            .collect::<io::Result<Vec<u64>>>()?;
+        if !keys.is_sorted() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "unsorted keys"));
+        }
+
+        let mut values = vec![0; len.div_ceil(4)];
+        reader.read_exact(&mut values)?;
+
+        Ok(SolvedTable { gaps, keys, values })
+    }
+
+    // Keys as little endian u64s followed by the packed values
+    pub fn write(&self, writer: &mut impl std::io::Write) -> std::io::Result<()> {
+        writer.write_all(&self.gaps.0.to_le_bytes())?;
+        writer.write_all(&(self.keys.len() as u64).to_le_bytes())?;
+        for key in self.keys.iter() {
+            writer.write_all(&key.to_le_bytes())?;
+        }
+        writer.write_all(&self.values)
+    }
+}
+
+// Ternary index of the stones on the playable squares, times two for the
+// side to move
+fn index(position: &Position) -> u64 {
+    let mut index = 0;
+    let mut power = 1;
+    for sq in !position.gaps {
+        let square = BitBoard::from_index(sq);
+        if !(position.black & square).is_empty() {
+            index += power;
+        } else if !(position.white & square).is_empty() {
+            index += 2 * power;
+        }
+        power *= 3;
+    }
+
+    index * 2 + (position.turn == Side::White) as u64
+}
+
+fn clear_counters(mut position: Position) -> Position {
+    position.half_moves = 0;
+    position.full_moves = 1;
+    position
+}
+
+fn terminal_value(outcome: Outcome, turn: Side) -> Value {
+    match (outcome, turn) {
+        (Outcome::Draw, _) => Value::Draw,
+        (Outcome::BlackWin, Side::Black) | (Outcome::WhiteWin, Side::White) => Value::Win,
+        _ => Value::Loss,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every value follows from the values of the children
+    fn check_consistency(table: &SolvedTable) {
+        for i in 0..table.len() {
+            let position = table.position(i);
+            assert_eq!(table.value(&position), Some(table.value_at(i)));
+
+            if let Some(outcome) = position.winner() {
+                assert_eq!(table.value_at(i), terminal_value(outcome, position.turn));
+                continue;
+            }
+
+            let children: Vec<Value> = position
+                .generate_moves()
+                .as_slice()
+                .iter()
+                .map(|mv| {
+                    let mut child = position;
+                    child.make_move(*mv);
+                    table.value(&child).unwrap()
+                })
+                .collect();
+
+            let expected = if children.contains(&Value::Loss) {
+                Value::Win
+            } else if children.iter().all(|v| *v == Value::Win) {
+                Value::Loss
+            } else {
+                Value::Draw
+            };
+            assert_eq!(table.value_at(i), expected);
+        }
+    }
+
+    #[test]
+    fn small_board_layout() {
+        let pos = small_board(7);
+        assert_eq!(pos, Position::default());
+
+        let pos = small_board(3);
+        assert_eq!((!pos.gaps).popcnt(), 9);
+        assert_eq!(pos.black.popcnt(), 2);
+        assert_eq!(pos.white.popcnt(), 2);
+    }
+
+    #[test]
+    fn solves_3x3() {
+        let start = small_board(3);
+        let table = SolvedTable::solve(&start);
+        assert!(table.len() > 1);
+        assert!(table.value(&start).is_some());
+        assert_eq!(table.counts().iter().sum::<usize>(), table.len());
+        check_consistency(&table);
+
+        let mut pos = start;
+        pos.make_move(table.best_move(&start).unwrap());
+        let value = table.value(&start).unwrap();
+        let expected = match value {
+            Value::Win => Value::Loss,
+            Value::Loss => Value::Win,
+            Value::Draw => Value::Draw,
+        };
+        assert_eq!(table.value(&pos), Some(expected));
+    }
+
+    #[test]
+    fn write_and_read() {
+        let table = SolvedTable::solve(&small_board(3));
+        let mut bytes = Vec::new();
+        table.write(&mut bytes).unwrap();
+
+        let read = SolvedTable::read(&mut bytes.as_slice()).unwrap();
+        assert_eq!(read.len(), table.len());
+        assert_eq!(read.counts(), table.counts());
+        assert_eq!(
+            read.best_move(&small_board(3)),
+            table.best_move(&small_board(3))
+        );
+
+        bytes.pop();
+        assert!(SolvedTable::read(&mut bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn solves_with_gaps() {
+        let mut start = small_board(3);
+        start.gaps |= BitBoard::from_square(1, 1);
+        let table = SolvedTable::solve(&start);
+        check_consistency(&table);
+    }
+}
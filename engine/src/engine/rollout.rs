@@ -0,0 +1,215 @@
+use super::{
+    eval,
+    moves::Move,
+    params::{RolloutPolicy, SearchParams},
+    rave::AmafMoves,
+};
+use crate::ataxx::position::{Outcome, Position};
+use fastrand::Rng;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Playout {
+    // 1.0 is a white win
+    pub value: f32,
+    // Only finished games can be drawn
+    pub draw: bool,
+}
+
+// Plays the position out to the end. Every move played is recorded in amaf.
+pub fn playout(
+    mut position: Position,
+    params: &SearchParams,
+    amaf: &mut AmafMoves,
+    rng: &mut Rng,
+) -> Playout {
+    let mut plies = 0;
+
+    while !position.game_over() {
+        if params.truncated && plies >= params.rollout_depth {
+            let eval = eval::evaluate(&position, params.evaluation);
+            return Playout {
+                value: eval::win_probability(eval, params.eval_scale),
+                draw: false,
+            };
+        }
+
+        let moves = position.generate_moves();
+        let mv = select_move(&position, moves.as_slice(), params, rng);
+        amaf.record(position.turn, mv);
+        position.make_move(mv);
+        plies += 1;
+    }
+
+    let outcome = position.winner().unwrap();
+    let result = match outcome {
+        Outcome::WhiteWin => 1.0,
+        Outcome::BlackWin => 0.0,
+        Outcome::Draw => 0.5,
+    };
+
+    let weight = params.margin_weight;
+    Playout {
+        value: (1.0 - weight) * result + weight * eval::margin(&position),
+        draw: outcome == Outcome::Draw,
+    }
+}
+
+fn select_move(position: &Position, moves: &[Move], params: &SearchParams, rng: &mut Rng) -> Move {
+    match params.rollout {
+        RolloutPolicy::Random => random(moves, rng),
+        RolloutPolicy::CaptureGreedy => greedy(position, moves, rng),
+        RolloutPolicy::EpsilonGreedy => {
+            if rng.f32() < params.epsilon {
+                random(moves, rng)
+            } else {
+                greedy(position, moves, rng)
+            }
+        }
+        RolloutPolicy::Softmax => softmax(position, moves, params.softmax_temp, rng),
+    }
+}
+
+fn random(moves: &[Move], rng: &mut Rng) -> Move {
+    moves[rng.usize(..moves.len())]
+}
+
+fn greedy(position: &Position, moves: &[Move], rng: &mut Rng) -> Move {
+    let mut best_gain = 0;
+    let mut best_move = moves[0];
+    let mut ties = 0;
+
+    for mv in moves.iter() {
+        let gain = position.move_gain(*mv);
+
+        if gain > best_gain || ties == 0 {
+            best_gain = gain;
+            best_move = *mv;
+            ties = 1;
+        } else if gain == best_gain {
+            // Reservoir sampling keeps ties uniformly random
+            ties += 1;
+            if rng.u32(..ties) == 0 {
+                best_move = *mv;
+            }
+        }
+    }
+
+    best_move
+}
+
+fn softmax(position: &Position, moves: &[Move], temperature: f32, rng: &mut Rng) -> Move {
+    let mut gains = [0; 256];
+    let mut weights = [0.0; 256];
+    let mut sum = 0.0;
+
+    for (i, mv) in moves.iter().enumerate() {
+        gains[i] = position.move_gain(*mv);
+    }
+
+    // Shifted by the best gain so cold temperatures don't overflow
+    let max_gain = *gains[..moves.len()].iter().max().unwrap();
+    for i in 0..moves.len() {
+        weights[i] = ((gains[i] as f32 - max_gain as f32) / temperature).exp();
+        sum += weights[i];
+    }
+
+    let mut target = rng.f32() * sum;
+    for (i, mv) in moves.iter().enumerate() {
+        target -= weights[i];
+        if target <= 0.0 {
+            return *mv;
+        }
+    }
+
+    moves[moves.len() - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn greedy_captures() {
+        let pos = Position::from_fen("7/7/7/7/7/oo5/1x5 x 0 1").unwrap();
+        let moves = pos.generate_moves();
+
+        for _ in 0..20 {
+            assert_eq!(
+                greedy(&pos, moves.as_slice(), &mut Rng::new()),
+                Move::new(0, 0)
+            );
+        }
+    }
+
+    #[test]
+    fn cold_softmax_is_greedy() {
+        let pos = Position::from_fen("7/7/7/7/7/oo5/1x5 x 0 1").unwrap();
+        let moves = pos.generate_moves();
+
+        for _ in 0..20 {
+            assert_eq!(
+                softmax(&pos, moves.as_slice(), 0.05, &mut Rng::new()),
+                Move::new(0, 0)
+            );
+        }
+    }
+
+    #[test]
+    fn playouts_finish() {
+        for policy in RolloutPolicy::ALL {
+            let params = SearchParams {
+                rollout: policy,
+                ..SearchParams::default()
+            };
+            let result = playout(
+                Position::default(),
+                &params,
+                &mut AmafMoves::default(),
+                &mut Rng::new(),
+            );
+            assert!(result.value == 0.0 || result.value == 0.5 || result.value == 1.0);
+            assert_eq!(result.draw, result.value == 0.5);
+        }
+    }
+
+    #[test]
+    fn margin_weighted_result() {
+        // Black wins 3 to 2
+        let pos = Position::from_fen("x5o/7/7/7/7/7/o4xx x 100 1").unwrap();
+        let result = |margin_weight| {
+            let params = SearchParams {
+                margin_weight,
+                ..SearchParams::default()
+            };
+            playout(pos, &params, &mut AmafMoves::default(), &mut Rng::new()).value
+        };
+
+        assert_eq!(result(0.0), 0.0);
+        assert_eq!(result(0.5), 0.2);
+        assert_eq!(result(1.0), 0.4);
+    }
+
+    #[test]
+    fn truncated_playouts() {
+        let pos = Position::from_fen("x5o/7/7/7/7/7/oo4x x 0 1").unwrap();
+        let params = SearchParams {
+            truncated: true,
+            rollout_depth: 0,
+            ..SearchParams::default()
+        };
+        assert_eq!(
+            playout(pos, &params, &mut AmafMoves::default(), &mut Rng::new()),
+            Playout {
+                value: eval::win_probability(1.0, 5.0),
+                draw: false
+            }
+        );
+
+        let params = SearchParams {
+            rollout_depth: 6,
+            ..params
+        };
+        let result = playout(pos, &params, &mut AmafMoves::default(), &mut Rng::new());
+        assert!((0.0..=1.0).contains(&result.value));
+    }
+}
@@ -21,7 +21,22 @@ impl<T: Copy, const N: usize> StaticVec<T, N> {
         self.len
     }
 
+    pub fn retain(&mut self, keep: impl Fn(&T) -> bool) {
+        let mut len = 0;
+        for i in 0..self.len {
+            if keep(&self.data[i]) {
+                self.data[len] = self.data[i];
+                len += 1;
+            }
+        }
+        self.len = len;
+    }
+
     pub fn as_slice(&self) -> &[T] {
         &self.data[..self.len]
     }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        &mut self.data[..self.len]
+    }
 }
@@ -1,7 +1,16 @@
 mod ataxx;
 mod engine;
+mod tools;
 mod uai;
 
 fn main() {
-    uai::handler::main_loop();
+    let args: Vec<String> = std::env::args().collect();
+
+    match args.get(1).map(String::as_str) {
+        Some("bookbuild") => tools::bookbuild::run(&args[2..]),
+        Some("openings") => tools::openings::run(&args[2..]),
+        Some("rolloutbench") => tools::rolloutbench::run(&args[2..]),
+        Some("smallsolve") => tools::smallsolve::run(&args[2..]),
+        _ => uai::handler::main_loop(),
+    }
 }
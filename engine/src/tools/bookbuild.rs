@@ -0,0 +1,378 @@
+use super::flag;
+use crate::ataxx::{
+    position::{Outcome, Position, Side},
+    symmetry::SYMMETRIES,
+};
+use crate::engine::{
+    book::{Book, BookMove},
+    moves::Move,
+};
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::BufWriter,
+};
+
+const USAGE: &str = "Usage: kurt bookbuild <games> <book> [--fen fen] [--plies n] \
+                     [--min-games n] [--min-score percent] [--symmetry]";
+
+struct Game {
+    // Moves as written, resolved against the position during replay since
+    // singles may be written with both squares
+    moves: Vec<String>,
+    // Result given by the collection, used when the moves don't finish
+    // the game
+    result: Option<Outcome>,
+    // The GUI scores unfinished games by the stones on the board
+    by_stones: bool,
+}
+
+struct Filter {
+    plies: usize,
+    min_games: u32,
+    min_score: f32,
+    symmetry: bool,
+}
+
+// kurt bookbuild <games> <book> [--fen fen] [--plies n] [--min-games n]
+//                [--min-score percent] [--symmetry]
+// Builds an opening book from a PGN file or a CSV file written by the GUI.
+// Moves are counted up to the ply limit and kept if they were played often
+// enough and scored well enough for the side that played them.
+pub fn run(args: &[String]) {
+    let [games_path, book_path, ..] = args else {
+        println!("{}", USAGE);
+        return;
+    };
+
+    // The GUI doesn't store the start position
+    let start = match flag(args, "--fen").map(|fen| Position::from_fen(fen)) {
+        None => Position::default(),
+        Some(Ok(pos)) => pos,
+        Some(Err(_)) => {
+            println!("Failed to read fen");
+            return;
+        }
+    };
+
+    let filter = Filter {
+        plies: flag(args, "--plies")
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(16),
+        min_games: flag(args, "--min-games")
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(2),
+        min_score: flag(args, "--min-score")
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(0.0),
+        symmetry: args.iter().any(|a| a == "--symmetry"),
+    };
+
+    let text = match std::fs::read_to_string(games_path) {
+        Ok(text) => text,
+        Err(err) => {
+            println!("Failed to read {}: {}", games_path, err);
+            return;
+        }
+    };
+
+    let games = if text.starts_with("Result,") {
+        parse_csv(&text)
+    } else {
+        parse_pgn(&text)
+    };
+
+    let (book, used) = build(&games, &start, &filter);
+    println!("games {} used {}", games.len(), used);
+    println!("positions {}", book.len());
+
+    let written = File::create(book_path).and_then(|file| book.write(&mut BufWriter::new(file)));
+    if let Err(err) = written {
+        println!("Failed to write {}: {}", book_path, err);
+    }
+}
+
+// Returns the book and the number of games that could be replayed
+fn build(games: &[Game], start: &Position, filter: &Filter) -> (Book, usize) {
+    let mut stats: HashMap<(u64, Move), BookMove> = HashMap::new();
+    let mut used = 0;
+
+    for game in games {
+        let Some((played, outcome)) = replay(game, start) else {
+            continue;
+        };
+        used += 1;
+
+        for (position, mv) in played.iter().take(filter.plies) {
+            let syms = if filter.symmetry { SYMMETRIES } else { 1 };
+            // Symmetric positions must not count the same game twice
+            let keys: HashSet<(u64, Move)> = (0..syms)
+                .map(|sym| (position.transform(sym).hash(), mv.transform(sym)))
+                .collect();
+
+            for key in keys {
+                let entry = stats.entry(key).or_insert(BookMove {
+                    mv: key.1,
+                    weight: 0,
+                    wins: 0,
+                    draws: 0,
+                    losses: 0,
+                });
+                match (outcome, position.turn) {
+                    (Outcome::Draw, _) => entry.draws += 1,
+                    (Outcome::BlackWin, Side::Black) | (Outcome::WhiteWin, Side::White) => {
+                        entry.wins += 1
+                    }
+                    _ => entry.losses += 1,
+                }
+            }
+        }
+    }
+
+    let mut book = Book::default();
+    for ((hash, _), mut book_move) in stats {
+        let games = book_move.games();
+        let score = 100.0 * (book_move.wins as f32 + 0.5 * book_move.draws as f32) / games as f32;
+        if games >= filter.min_games && score >= filter.min_score {
+            book_move.weight = games;
+            book.add(hash, book_move);
+        }
+    }
+
+    (book, used)
+}
+
+// The positions with the move played in them, and the result. None if a
+// move is illegal or the result is unknown.
+fn replay(game: &Game, start: &Position) -> Option<(Vec<(Position, Move)>, Outcome)> {
+    let mut position = *start;
+    let mut played = Vec::new();
+
+    for text in game.moves.iter() {
+        if position.game_over() {
+            return None;
+        }
+        let mv = position.parse_move(text).ok()?;
+        played.push((position, mv));
+        position.make_move(mv);
+    }
+
+    let outcome = position
+        .winner()
+        .or(game.result)
+        .or_else(|| game.by_stones.then(|| stone_outcome(&position)))?;
+    Some((played, outcome))
+}
+
+fn stone_outcome(position: &Position) -> Outcome {
+    match position.black.popcnt().cmp(&position.white.popcnt()) {
+        Ordering::Greater => Outcome::BlackWin,
+        Ordering::Less => Outcome::WhiteWin,
+        Ordering::Equal => Outcome::Draw,
+    }
+}
+
+// Result,Player,Engine,Moves with space separated moves. The GUI counts
+// ranks from the top and always writes both squares, even for singles.
+fn parse_csv(text: &str) -> Vec<Game> {
+    text.lines()
+        .skip(1)
+        .filter_map(|line| {
+            let moves = line.splitn(4, ',').nth(3)?;
+            let moves = moves
+                .split_whitespace()
+                .map(|text| csv_move(text).map(|mv| mv.to_string()))
+                .collect::<Option<Vec<String>>>()?;
+
+            Some(Game {
+                moves,
+                result: None,
+                by_stones: true,
+            })
+        })
+        .collect()
+}
+
+fn csv_move(text: &str) -> Option<Move> {
+    if text == "0000" {
+        return Some(Move::pass());
+    }
+
+    let square = |file: u8, rank: u8| {
+        let (file, rank) = (file.checked_sub(b'a')?, rank.checked_sub(b'1')?);
+        (file < 7 && rank < 7).then_some((file, 6 - rank))
+    };
+
+    let &[from_file, from_rank, to_file, to_rank] = text.as_bytes() else {
+        return None;
+    };
+    let from = square(from_file, from_rank)?;
+    let to = square(to_file, to_rank)?;
+    let to_index = to.1 * 7 + to.0;
+
+    if from.0.abs_diff(to.0) <= 1 && from.1.abs_diff(to.1) <= 1 {
+        Some(Move::new(to_index, to_index))
+    } else {
+        Some(Move::new(from.1 * 7 + from.0, to_index))
+    }
+}
+
+// Tag pairs are skipped apart from Result, where 1-0 is a win for the side
+// that moved first. Comments and move numbers are ignored.
+fn parse_pgn(text: &str) -> Vec<Game> {
+    let mut games = Vec::new();
+    let mut moves = Vec::new();
+    let mut result = None;
+    let mut in_comment = false;
+
+    let mut finish = |moves: &mut Vec<String>, result: &mut Option<Outcome>| {
+        if !moves.is_empty() {
+            games.push(Game {
+                moves: std::mem::take(moves),
+                result: *result,
+                by_stones: false,
+            });
+        }
+        *result = None;
+    };
+
+    for line in text.lines() {
+        let line = line.trim();
+        if !in_comment && line.starts_with('[') {
+            // A new tag section starts the next game
+            if !moves.is_empty() {
+                finish(&mut moves, &mut result);
+            }
+            if let Some(value) = line.strip_prefix("[Result \"") {
+                result = match value.trim_end_matches(['"', ']']) {
+                    "1-0" => Some(Outcome::BlackWin),
+                    "0-1" => Some(Outcome::WhiteWin),
+                    "1/2-1/2" => Some(Outcome::Draw),
+                    _ => None,
+                };
+            }
+            continue;
+        }
+
+        for token in line.split_whitespace() {
+            if in_comment {
+                in_comment = !token.ends_with('}');
+                continue;
+            }
+            if token.starts_with('{') {
+                in_comment = !token.ends_with('}');
+                continue;
+            }
+
+            // Move numbers may be glued to the move, as in 1.f2
+            let token = token.rsplit('.').next().unwrap();
+            match token {
+                "" => (),
+                "1-0" | "0-1" | "1/2-1/2" | "*" => finish(&mut moves, &mut result),
+                _ => moves.push(token.to_string()),
+            }
+        }
+    }
+    finish(&mut moves, &mut result);
+
+    games
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_moves() {
+        // The GUI's a1 is the engine's a7
+        assert_eq!(csv_move("a1b2"), Some(Move::new(36, 36)));
+        assert_eq!(csv_move("a1a1"), Some(Move::new(42, 42)));
+        assert_eq!(csv_move("g7g5"), Some(Move::new(6, 20)));
+        assert_eq!(csv_move("0000"), Some(Move::pass()));
+        assert_eq!(csv_move("h1a1"), None);
+
+        let games = parse_csv("Result,Player,Engine,Moves\nW,10,5,a1b2 g7g5\n");
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].moves, ["b6", "g1g3"]);
+    }
+
+    #[test]
+    fn pgn_games() {
+        let text = "[Event \"test\"]\n\
+                    [Result \"0-1\"]\n\
+                    \n\
+                    1. f2 {a comment} b6 2. g1g3 0-1\n\
+                    \n\
+                    [Result \"1/2-1/2\"]\n\
+                    \n\
+                    1.b6 1/2-1/2\n";
+        let games = parse_pgn(text);
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].moves, ["f2", "b6", "g1g3"]);
+        assert_eq!(games[0].result, Some(Outcome::WhiteWin));
+        assert_eq!(games[1].moves, ["b6"]);
+        assert_eq!(games[1].result, Some(Outcome::Draw));
+    }
+
+    #[test]
+    fn builds_filtered_book() {
+        let game = |moves: &str, result| Game {
+            moves: moves.split(' ').map(String::from).collect(),
+            result: Some(result),
+            by_stones: false,
+        };
+        let games = [
+            game("f2 f6", Outcome::BlackWin),
+            game("f2 b2", Outcome::BlackWin),
+            game("b6 b2", Outcome::WhiteWin),
+            // b7 is illegal for white here
+            game("f2 b7", Outcome::BlackWin),
+        ];
+        let start = Position::default();
+        let mut filter = Filter {
+            plies: 1,
+            min_games: 2,
+            min_score: 0.0,
+            symmetry: false,
+        };
+
+        let (book, used) = build(&games, &start, &filter);
+        assert_eq!(used, 3);
+        let mut rng = fastrand::Rng::with_seed(1);
+        let book_move = book.probe(&start, &mut rng).unwrap();
+        assert_eq!(book_move.mv, Move::new(12, 12));
+        assert_eq!((book_move.weight, book_move.wins), (2, 2));
+
+        // b6 and f2 are the same move under symmetry, b6 lost once
+        filter.symmetry = true;
+        filter.min_games = 3;
+        let (book, _) = build(&games, &start, &filter);
+        let book_move = book.probe(&start, &mut rng).unwrap();
+        assert_eq!(book_move.games(), 3);
+        assert_eq!(book_move.losses, 1);
+
+        filter.min_score = 70.0;
+        let (book, _) = build(&games, &start, &filter);
+        assert_eq!(book.len(), 0);
+    }
+
+    #[test]
+    fn pgn_singles_with_both_squares() {
+        let text = "[Result \"1-0\"]\n1. g1f2 a1b2 1-0\n\n[Result \"1-0\"]\n1. f2 b2 1-0\n";
+        let games = parse_pgn(text);
+        let filter = Filter {
+            plies: 2,
+            min_games: 2,
+            min_score: 0.0,
+            symmetry: false,
+        };
+
+        let (book, used) = build(&games, &Position::default(), &filter);
+        assert_eq!(used, 2);
+        let mut rng = fastrand::Rng::with_seed(1);
+        let book_move = book.probe(&Position::default(), &mut rng).unwrap();
+        assert_eq!(book_move.mv, Move::new(12, 12));
+        assert_eq!(book_move.games(), 2);
+    }
+}
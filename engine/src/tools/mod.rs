@@ -0,0 +1,12 @@
+pub mod bookbuild;
+pub mod openings;
+pub mod rolloutbench;
+mod selfplay;
+pub mod smallsolve;
+
+// The value following a command line flag, as in --plies 8
+fn flag<'a>(args: &'a [String], name: &str) -> Option<&'a String> {
+    args.iter()
+        .position(|a| a == name)
+        .and_then(|i| args.get(i + 1))
+}
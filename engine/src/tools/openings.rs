@@ -0,0 +1,254 @@
+use super::flag;
+use crate::ataxx::{bitboard::BitBoard, position::Position};
+use crate::engine::{limits::SearchLimits, mcts::Tree, params::SearchParams};
+use fastrand::Rng;
+use std::io::Write;
+
+const USAGE: &str = "Usage: kurt openings <count> [--layout mirror|rotation] [--gaps n] \
+                     [--plies n] [--nodes n] [--threshold percent] [--seed n] [--out file]";
+
+// Gives up on a layout or an opening after this many tries in a row
+const MAX_TRIES: usize = 1000;
+
+// Both groups contain a symmetry that swaps the colours of the start
+// stones, so neither side gets the better gaps
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Layout {
+    Mirror,
+    Rotation,
+}
+
+impl Layout {
+    fn symmetries(&self) -> [usize; 4] {
+        match self {
+            Layout::Mirror => [0, 1, 2, 3],
+            Layout::Rotation => [0, 3, 5, 6],
+        }
+    }
+}
+
+// kurt openings <count> [--layout mirror|rotation] [--gaps n] [--plies n]
+//               [--nodes n] [--threshold percent] [--seed n] [--out file]
+// Generates random symmetric gap layouts and plays random moves from them.
+// A short search scores every opening, only those within the threshold of
+// an even game are printed as fens.
+pub fn run(args: &[String]) {
+    let Some(count) = args.first().and_then(|c| c.parse::<usize>().ok()) else {
+        println!("{}", USAGE);
+        return;
+    };
+
+    let layout = match flag(args, "--layout").map(String::as_str) {
+        None | Some("mirror") => Layout::Mirror,
+        Some("rotation") => Layout::Rotation,
+        Some(other) => {
+            println!("Unknown layout {}", other);
+            return;
+        }
+    };
+    let gaps: u32 = flag(args, "--gaps")
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(4);
+    let plies: usize = flag(args, "--plies")
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(4);
+    let nodes: u64 = flag(args, "--nodes")
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(2000);
+    let threshold: f32 = flag(args, "--threshold")
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(5.0);
+    let seed: u64 = flag(args, "--seed")
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(0);
+
+    let mut rng = if seed == 0 {
+        Rng::new()
+    } else {
+        Rng::with_seed(seed)
+    };
+    let params = SearchParams {
+        seed,
+        ..SearchParams::default()
+    };
+
+    let mut fens = Vec::new();
+    let mut tries = 0;
+    while fens.len() < count && tries < MAX_TRIES {
+        tries += 1;
+
+        // Keep the openings found so far
+        let Some(start) = random_layout(&mut rng, layout, gaps) else {
+            println!("No playable layout with {} gaps", gaps);
+            break;
+        };
+        let Some(opening) = random_opening(&mut rng, &start, plies) else {
+            continue;
+        };
+
+        if is_balanced(&opening, &params, nodes, threshold) {
+            fens.push(opening.get_fen());
+            tries = 0;
+        }
+    }
+
+    if fens.len() < count {
+        println!("Only found {} balanced openings", fens.len());
+    }
+
+    match flag(args, "--out") {
+        None => fens.iter().for_each(|fen| println!("{}", fen)),
+        Some(path) => {
+            let written = std::fs::File::create(path)
+                .and_then(|mut file| fens.iter().try_for_each(|fen| writeln!(file, "{}", fen)));
+            match written {
+                Ok(()) => println!("Wrote {} openings to {}", fens.len(), path),
+                Err(err) => println!("Failed to write {}: {}", path, err),
+            }
+        }
+    }
+}
+
+// Start position with at least the given number of gaps, placed in orbits
+// of the layout's symmetries
+fn random_layout(rng: &mut Rng, layout: Layout, gaps: u32) -> Option<Position> {
+    let start = Position::default();
+    let corners = start.black | start.white;
+    let free = (!corners).popcnt();
+
+    for _ in 0..MAX_TRIES {
+        let mut board = BitBoard(0);
+        while board.popcnt() < gaps.min(free) {
+            let sq = rng.u8(..49);
+            if !(corners & BitBoard::from_index(sq)).is_empty() {
+                continue;
+            }
+
+            for sym in layout.symmetries() {
+                board |= BitBoard::from_index(sq).transform(sym);
+            }
+        }
+
+        let position = Position {
+            gaps: board,
+            ..start
+        };
+        if all_reachable(&position) {
+            return Some(position);
+        }
+    }
+
+    None
+}
+
+// Every empty square can be reached from the start stones, so the gaps
+// don't cut off part of the board
+fn all_reachable(position: &Position) -> bool {
+    let playable = !position.gaps;
+    let mut reached = position.both_sides();
+
+    loop {
+        let next = (reached | reached.reach()) & playable;
+        if next == reached {
+            return reached == playable;
+        }
+        reached = next;
+    }
+}
+
+// Uniformly random moves, None if the game ends on the way
+fn random_opening(rng: &mut Rng, start: &Position, plies: usize) -> Option<Position> {
+    let mut position = *start;
+
+    for _ in 0..plies {
+        if position.game_over() {
+            return None;
+        }
+
+        let moves = position.generate_moves();
+        position.make_move(moves.as_slice()[rng.usize(..moves.len())]);
+    }
+
+    (!position.game_over()).then_some(position)
+}
+
+// Threshold in percent away from an even score
+// Short searches lean towards one side even on symmetric boards, so the
+// opening is compared with its colour-swapped twin, where the same lean
+// cancels out
+fn is_balanced(position: &Position, params: &SearchParams, nodes: u64, threshold: f32) -> bool {
+    let value = |position: Position| {
+        let mut tree = Tree::with_params(params.clone());
+        tree.uct(position, SearchLimits::from_nodes(nodes));
+        tree.root_value()
+    };
+    let swapped = Position {
+        black: position.white,
+        white: position.black,
+        ..*position
+    };
+
+    match (value(*position), value(swapped)) {
+        (Some(value), Some(swapped)) => 100.0 * (value - swapped).abs() / 2.0 <= threshold,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn symmetric_layouts() {
+        let mut rng = Rng::with_seed(3);
+
+        for layout in [Layout::Mirror, Layout::Rotation] {
+            for gaps in [0, 4, 12] {
+                let pos = random_layout(&mut rng, layout, gaps).unwrap();
+                assert!(pos.gaps.popcnt() >= gaps);
+                assert!(all_reachable(&pos));
+                assert!((pos.gaps & pos.both_sides()).is_empty());
+
+                for sym in layout.symmetries() {
+                    assert_eq!(pos.gaps.transform(sym), pos.gaps);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn cut_off_squares() {
+        // The centre can't be reached past two rings of gaps
+        let pos =
+            Position::from_fen("x5o/1-----1/1-----1/1--1--1/1-----1/1-----1/o5x x 0 1").unwrap();
+        assert!(!all_reachable(&pos));
+        assert!(all_reachable(&Position::default()));
+    }
+
+    #[test]
+    fn random_openings() {
+        let mut rng = Rng::with_seed(7);
+        let pos = random_opening(&mut rng, &Position::default(), 4).unwrap();
+        assert_eq!(pos.half_moves, 4);
+        assert_eq!(pos.turn, Position::default().turn);
+    }
+
+    #[test]
+    fn balance_filter() {
+        // Black is far ahead
+        let won = Position::from_fen("xxxxxxx/xxxxxxx/xxxxxxx/xxxxxxx/xxxxxxx/7/o6 x 0 1").unwrap();
+        let params = SearchParams {
+            seed: 1,
+            ..SearchParams::default()
+        };
+        assert!(!is_balanced(&won, &params, 200, 5.0));
+        // The symmetric start position is even at the default threshold
+        for seed in 1..=5 {
+            let params = SearchParams {
+                seed,
+                ..SearchParams::default()
+            };
+            assert!(is_balanced(&Position::default(), &params, 200, 5.0));
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use super::selfplay::play_game;
+use crate::ataxx::position::{Outcome, Position};
+use crate::engine::params::{RolloutPolicy, SearchParams};
+
+const OPENINGS: [&str; 3] = [
+    "x5o/7/7/7/7/7/o5x x 0 1",
+    "x5o/7/2-1-2/7/2-1-2/7/o5x x 0 1",
+    "x2-2o/7/7/-5-/7/7/o2-2x x 0 1",
+];
+
+#[derive(Default)]
+struct Record {
+    wins: u32,
+    draws: u32,
+    losses: u32,
+}
+
+impl Record {
+    fn score(&self) -> f32 {
+        let games = self.wins + self.draws + self.losses;
+        if games == 0 {
+            return 0.0;
+        }
+
+        100.0 * (self.wins as f32 + 0.5 * self.draws as f32) / games as f32
+    }
+}
+
+// kurt rolloutbench [games per pairing] [movetime in ms]
+// Round robin between the rollout policies, colours alternate every game
+pub fn run(args: &[String]) {
+    let games: usize = args.first().and_then(|a| a.parse().ok()).unwrap_or(10);
+    let move_time: u128 = args.get(1).and_then(|a| a.parse().ok()).unwrap_or(100);
+    let policies = RolloutPolicy::ALL;
+    let mut records: Vec<Record> = policies.iter().map(|_| Record::default()).collect();
+
+    for a in 0..policies.len() {
+        for b in a + 1..policies.len() {
+            for game in 0..games {
+                let pos = Position::from_fen(OPENINGS[(game / 2) % OPENINGS.len()]).unwrap();
+                let (black, white) = if game % 2 == 0 { (a, b) } else { (b, a) };
+                let black_params = SearchParams {
+                    rollout: policies[black],
+                    ..SearchParams::default()
+                };
+                let white_params = SearchParams {
+                    rollout: policies[white],
+                    ..SearchParams::default()
+                };
+
+                match play_game(pos, &black_params, &white_params, move_time) {
+                    Outcome::BlackWin => {
+                        records[black].wins += 1;
+                        records[white].losses += 1;
+                    }
+                    Outcome::WhiteWin => {
+                        records[white].wins += 1;
+                        records[black].losses += 1;
+                    }
+                    Outcome::Draw => {
+                        records[black].draws += 1;
+                        records[white].draws += 1;
+                    }
+                }
+
+                println!(
+                    "{} vs {} game {} done",
+                    policies[a].name(),
+                    policies[b].name(),
+                    game + 1
+                );
+            }
+        }
+    }
+
+    println!(
+        "{:<16}{:>6}{:>6}{:>6}{:>8}",
+        "policy", "W", "D", "L", "score"
+    );
+    for (policy, record) in policies.iter().zip(records.iter()) {
+        println!(
+            "{:<16}{:>6}{:>6}{:>6}{:>7.1}%",
+            policy.name(),
+            record.wins,
+            record.draws,
+            record.losses,
+            record.score()
+        );
+    }
+}
@@ -0,0 +1,22 @@
+use crate::ataxx::position::{Outcome, Position, Side};
+use crate::engine::{limits::SearchLimits, mcts::Tree, params::SearchParams};
+
+// Plays one engine vs engine game with a fixed time per move
+pub fn play_game(
+    mut pos: Position,
+    black: &SearchParams,
+    white: &SearchParams,
+    move_time: u128,
+) -> Outcome {
+    while !pos.game_over() {
+        let params = match pos.turn {
+            Side::Black => black,
+            Side::White => white,
+        };
+
+        let mv = Tree::with_params(params.clone()).uct(pos, SearchLimits::from_time(move_time));
+        pos.make_move(mv);
+    }
+
+    pos.winner().unwrap()
+}
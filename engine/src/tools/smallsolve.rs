@@ -0,0 +1,105 @@
+use super::flag;
+use crate::ataxx::position::Position;
+use crate::engine::{
+    limits::SearchLimits,
+    mcts::Tree,
+    params::SearchParams,
+    retrograde::{small_board, SolvedTable, Value},
+};
+use std::{fs::File, io::BufWriter};
+
+// Positions the search is checked on
+const CHECK_POSITIONS: usize = 20;
+// Every position is enumerated in memory, a 4x4 board already has close to
+// 18 million and takes minutes
+const MAX_SIZE: u8 = 4;
+
+// kurt smallsolve <size | fen> [--out file] [--check movetime]
+// Solves a small board, given by its size or a fen with gaps. The search
+// can be checked against the solution on decided positions.
+pub fn run(args: &[String]) {
+    let Some(board) = args.first() else {
+        println!("Usage: kurt smallsolve <size | fen> [--out file] [--check movetime]");
+        return;
+    };
+
+    let start = match board.parse::<u8>() {
+        Ok(size) if (2..=MAX_SIZE).contains(&size) => small_board(size),
+        Ok(_) => {
+            println!("Board size must be between 2 and {}", MAX_SIZE);
+            return;
+        }
+        Err(_) => match Position::from_fen(board) {
+            Ok(pos) => pos,
+            Err(_) => {
+                println!("Failed to read fen");
+                return;
+            }
+        },
+    };
+
+    let squares = (!start.gaps).popcnt();
+    if squares > (MAX_SIZE * MAX_SIZE) as u32 {
+        println!(
+            "{} playable squares, at most {} can be solved",
+            squares,
+            MAX_SIZE * MAX_SIZE
+        );
+        return;
+    }
+
+    let table = SolvedTable::solve(&start);
+    let [wins, draws, losses] = table.counts();
+    println!("positions {}", table.len());
+    println!("wins {} draws {} losses {}", wins, draws, losses);
+    println!("start {:?}", table.value(&start).unwrap());
+    if let Some(mv) = table.best_move(&start) {
+        println!("bestmove {}", mv);
+    }
+
+    if let Some(path) = flag(args, "--out") {
+        let written = File::create(path).and_then(|file| table.write(&mut BufWriter::new(file)));
+        if let Err(err) = written {
+            println!("Failed to write {}: {}", path, err);
+        }
+    }
+
+    if let Some(move_time) = flag(args, "--check").and_then(|t| t.parse::<u128>().ok()) {
+        check_search(&table, move_time);
+    }
+}
+
+// Runs the search on won positions spread over the table and counts how
+// often it keeps the win
+fn check_search(table: &SolvedTable, move_time: u128) {
+    let won: Vec<Position> = (0..table.len())
+        .map(|i| table.position(i))
+        .filter(|pos| !pos.game_over() && table.value(pos) == Some(Value::Win))
+        .collect();
+    if won.is_empty() {
+        println!("No won positions to check");
+        return;
+    }
+
+    let step = (won.len() / CHECK_POSITIONS).max(1);
+    let mut checked = 0;
+    let mut kept = 0;
+
+    for pos in won.iter().step_by(step).take(CHECK_POSITIONS) {
+        // Alpha-beta would decide most small positions on its own
+        let params = SearchParams {
+            endgame_empties: 0,
+            ..SearchParams::default()
+        };
+        let mv = Tree::with_params(params).uct(*pos, SearchLimits::from_time(move_time));
+        let mut child = *pos;
+        child.make_move(mv);
+
+        checked += 1;
+        if table.value(&child) == Some(Value::Loss) {
+            kept += 1;
+        }
+    }
+
+    println!("search kept the win in {}/{} positions", kept, checked);
+}
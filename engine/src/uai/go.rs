@@ -0,0 +1,140 @@
+use crate::ataxx::position::{Position, Side};
+use crate::engine::limits::SearchLimits;
+
+// Search time of a go command without any limit
+const DEFAULT_MOVE_TIME: u128 = 5000;
+
+const KEYWORDS: [&str; 12] = [
+    "movetime",
+    "nodes",
+    "depth",
+    "infinite",
+    "searchmoves",
+    "btime",
+    "wtime",
+    "binc",
+    "winc",
+    "movestogo",
+    "mate",
+    "ponder",
+];
+
+#[derive(Debug, PartialEq)]
+pub enum GoError {
+    InvalidValue,
+    IllegalMove,
+}
+
+// go [movetime ms] [nodes n] [depth n] [infinite] [searchmoves move ...]
+//    [btime ms] [wtime ms] [binc ms] [winc ms] [movestogo n]
+// Only the clock of the side to move is used. Without any limit the search
+// runs for a fixed time, infinite searches until it is stopped.
+pub fn parse_go(token: &[&str], pos: &Position) -> Result<SearchLimits, GoError> {
+    let mut limits = SearchLimits::default();
+    let mut infinite = false;
+    let mut i = 1;
+
+    while i < token.len() {
+        let value = token.get(i + 1).copied().unwrap_or_default();
+        let (black, white) = (pos.turn == Side::Black, pos.turn == Side::White);
+
+        match token[i] {
+            "movetime" => limits.move_time = Some(parse(value)?),
+            "nodes" => limits.nodes = Some(parse(value)?),
+            "depth" => limits.depth = Some(parse(value)?),
+            "btime" if black => limits.time_left = Some(parse(value)?),
+            "wtime" if white => limits.time_left = Some(parse(value)?),
+            "binc" if black => limits.increment = parse(value)?,
+            "winc" if white => limits.increment = parse(value)?,
+            "movestogo" => limits.moves_to_go = Some(parse(value)?),
+            // The other side's clock and mate searches are handled elsewhere
+            "btime" | "wtime" | "binc" | "winc" | "mate" => (),
+            "infinite" => {
+                infinite = true;
+                i += 1;
+                continue;
+            }
+            "searchmoves" => {
+                i += 1;
+                while i < token.len() && !KEYWORDS.contains(&token[i]) {
+                    let mv = pos.parse_move(token[i]).map_err(|_| GoError::IllegalMove)?;
+                    limits.search_moves.push(mv);
+                    i += 1;
+                }
+                continue;
+            }
+            _ => {
+                i += 1;
+                continue;
+            }
+        }
+
+        i += 2;
+    }
+
+    let limited = limits.move_time.is_some()
+        || limits.nodes.is_some()
+        || limits.depth.is_some()
+        || limits.time_left.is_some();
+    if !limited && !infinite {
+        limits.move_time = Some(DEFAULT_MOVE_TIME);
+    }
+
+    Ok(limits)
+}
+
+fn parse<T: std::str::FromStr>(value: &str) -> Result<T, GoError> {
+    value.parse().map_err(|_| GoError::InvalidValue)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::moves::Move;
+
+    fn go(cmd: &str, pos: &Position) -> Result<SearchLimits, GoError> {
+        let token: Vec<&str> = cmd.split_whitespace().collect();
+        parse_go(&token, pos)
+    }
+
+    #[test]
+    fn limits() {
+        let pos = Position::default();
+        assert_eq!(go("go", &pos), Ok(SearchLimits::from_time(5000)));
+        assert_eq!(go("go infinite", &pos), Ok(SearchLimits::default()));
+        assert_eq!(go("go nodes 800", &pos), Ok(SearchLimits::from_nodes(800)));
+
+        let limits = go("go movetime 200 depth 6 searchmoves f2 g1g3 nodes 50", &pos).unwrap();
+        assert_eq!(limits.move_time, Some(200));
+        assert_eq!(limits.depth, Some(6));
+        assert_eq!(limits.nodes, Some(50));
+        assert_eq!(
+            limits.search_moves,
+            vec![Move::new(12, 12), Move::new(6, 20)]
+        );
+    }
+
+    #[test]
+    fn clocks() {
+        let cmd = "go btime 1000 wtime 2000 binc 10 winc 20 movestogo 5";
+        let limits = go(cmd, &Position::default()).unwrap();
+        assert_eq!(limits.time_left, Some(1000));
+        assert_eq!(limits.increment, 10);
+        assert_eq!(limits.moves_to_go, Some(5));
+        assert_eq!(limits.move_time, None);
+
+        let mut pos = Position::default();
+        pos.make_move(Move::new(12, 12));
+        let limits = go(cmd, &pos).unwrap();
+        assert_eq!(limits.time_left, Some(2000));
+        assert_eq!(limits.increment, 20);
+    }
+
+    #[test]
+    fn go_errors() {
+        let pos = Position::default();
+        assert_eq!(go("go movetime", &pos), Err(GoError::InvalidValue));
+        assert_eq!(go("go nodes -5", &pos), Err(GoError::InvalidValue));
+        assert_eq!(go("go searchmoves d4", &pos), Err(GoError::IllegalMove));
+    }
+}
@@ -1,57 +1,336 @@
-use crate::ataxx::position::Position;
-use crate::engine::mcts::Tree;
+use super::{go, options};
+use crate::ataxx::{
+    position::Position,
+    startpos::{self, CATALOGUE},
+};
+use crate::engine::{
+    book::Book,
+    limits::{Abort, SearchLimits},
+    mcts::{self, Tree},
+    moves::Move,
+    params::SearchParams,
+    pns::{self, MateResult},
+    retrograde::SolvedTable,
+};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+// How often an infinite search that already finished looks for a stop
+const STOP_POLL: Duration = Duration::from_millis(10);
+
+// A go command running on its own thread, so input is read meanwhile.
+// The thread prints bestmove when it is done.
+struct Search {
+    stop: Arc<AtomicBool>,
+    infinite: bool,
+    thread: JoinHandle<()>,
+}
+
+impl Search {
+    fn start(
+        pos: Position,
+        limits: SearchLimits,
+        mate: Option<u32>,
+        params: SearchParams,
+        book: Arc<Book>,
+        table: Option<Arc<SolvedTable>>,
+    ) -> Search {
+        let mut tree = Tree::with_params(params.clone());
+        tree.set_info(true);
+        let stop = tree.stop_signal();
+        let infinite = limits.is_infinite();
+
+        let stop_signal = stop.clone();
+        let thread = std::thread::spawn(move || {
+            let tables = (book.as_ref(), table.as_deref());
+            let mv = find_move(&mut tree, pos, limits, mate, &params, tables);
+
+            // Infinite searches only answer once they are told to stop
+            while infinite && !stop_signal.load(Ordering::Relaxed) {
+                std::thread::sleep(STOP_POLL);
+            }
+            println!("bestmove {}", mv);
+        });
+
+        Search {
+            stop,
+            infinite,
+            thread,
+        }
+    }
+
+    // Ends the search early, bestmove is printed before this returns
+    fn stop(self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.wait();
+    }
+
+    fn wait(self) {
+        self.thread.join().unwrap();
+    }
+}
 
 pub fn main_loop() {
     let mut pos = Position::default();
+    let mut params = SearchParams::default();
+    let mut book = Arc::new(Book::default());
+    let mut table: Option<Arc<SolvedTable>> = None;
+    let mut search: Option<Search> = None;
 
     loop {
         let mut input = String::new();
-        std::io::stdin().read_line(&mut input).unwrap();
+        // End of input, a limited search may still finish
+        if std::io::stdin().read_line(&mut input).unwrap_or(0) == 0 {
+            match search.take() {
+                Some(search) if search.infinite => search.stop(),
+                Some(search) => search.wait(),
+                None => (),
+            }
+            return;
+        }
+
         let token: Vec<&str> = input.split_whitespace().collect();
+        let Some(&command) = token.first() else {
+            continue;
+        };
 
-        match token[0] {
+        match command {
             "uai" => {
                 println!("id name Kurt");
                 println!("id author Cristopher Torgrip");
+                options::print_options();
                 println!("uaiok");
             }
 
+            "setoption" => {
+                let book_file = params.book_file.clone();
+                let table_file = params.table_file.clone();
+                if options::set_option(&mut params, &token).is_err() {
+                    println!("Failed to set option");
+                } else if params.book_file != book_file {
+                    book = Arc::new(load_book(&params.book_file));
+                } else if params.table_file != table_file {
+                    table = load_table(&params.table_file).map(Arc::new);
+                }
+            }
+
             "isready" => {
                 println!("readyok");
             }
 
             "position" => {
-                let mut fen = String::new();
-
-                if token[1] == "fen" {
-                    for f in token.iter().skip(2) {
-                        fen.push_str(format!("{} ", f).as_str());
-                    }
-                } else if token[1] == "startpos" {
-                    fen = "x5o/7/7/7/7/7/o5x x 0 1".to_string();
-                } else {
-                    continue;
+                if let Some(new_pos) = parse_position(&token, params.start_pos) {
+                    pos = new_pos;
                 }
+            }
 
-                println!("{}", fen);
-                fen = fen.trim().to_owned();
+            // Lists the named start positions
+            "startpos" => {
+                for start in CATALOGUE.iter() {
+                    println!("startpos {} fen {}", start.name, start.fen);
+                }
+            }
 
-                match Position::from_fen(&fen) {
-                    Ok(_) => pos = Position::from_fen(&fen).unwrap(),
-                    Err(_) => {
-                        println!("Failed to read fen");
+            "go" => {
+                let limits = match go::parse_go(&token, &pos) {
+                    Ok(limits) => limits,
+                    Err(err) => {
+                        println!("info string invalid go command: {:?}", err);
                         continue;
                     }
+                };
+
+                // go mate <moves>, falls back to a normal search without a win
+                let mate = token
+                    .iter()
+                    .position(|t| *t == "mate")
+                    .and_then(|i| token.get(i + 1))
+                    .and_then(|n| n.parse::<u32>().ok());
+
+                // A search still running answers before the new one starts
+                if let Some(search) = search.take() {
+                    search.stop();
                 }
+                search = Some(Search::start(
+                    pos,
+                    limits,
+                    mate,
+                    params.clone(),
+                    book.clone(),
+                    table.clone(),
+                ));
             }
 
-            "go" => {
-                let mut tree = Tree::new();
-                let mv = tree.uct(pos, 5000);
-                println!("bestmove {}", mv);
+            "stop" => {
+                if let Some(search) = search.take() {
+                    search.stop();
+                }
+            }
+
+            "quit" => {
+                if let Some(search) = search.take() {
+                    search.stop();
+                }
+                return;
             }
 
             _ => continue,
         }
     }
 }
+
+// Mate search, solved table, book and tree search, in that order
+fn find_move(
+    tree: &mut Tree,
+    pos: Position,
+    mut limits: SearchLimits,
+    mate: Option<u32>,
+    params: &SearchParams,
+    (book, table): (&Book, Option<&SolvedTable>),
+) -> Move {
+    // The mate search gets half the time budget and answers to stop, the
+    // tree search makes do with what is left on the clock if it fails
+    if let Some(moves) = mate {
+        let time = Instant::now();
+        let stop = tree.stop_signal();
+        let abort = Abort {
+            stop: Some(&stop),
+            deadline: limits
+                .time_budget()
+                .map(|budget| time + Duration::from_millis((budget / 2) as u64)),
+        };
+        if let MateResult::Mate(n, mv) = pns::find_mate(&pos, moves, params.pns_memory, abort) {
+            println!("info score mate {}", n);
+            return mv;
+        }
+
+        let elapsed = time.elapsed().as_millis();
+        limits.move_time = limits.move_time.map(|t| t.saturating_sub(elapsed));
+        limits.time_left = limits.time_left.map(|t| t.saturating_sub(elapsed));
+    }
+
+    // Small boards the table covers are played perfectly, but its moves
+    // may lie outside searchmoves just like book moves
+    let solved = table
+        .filter(|_| limits.search_moves.is_empty())
+        .and_then(|table| Some((table.best_move(&pos)?, table.value(&pos)?)));
+    if let Some((mv, value)) = solved {
+        println!("info string table move {} value {:?}", mv, value);
+        return mv;
+    }
+
+    let mut rng = mcts::search_rng(params.seed, &pos);
+    // Book moves may lie outside searchmoves
+    let book_move = if limits.search_moves.is_empty() {
+        book.probe(&pos, &mut rng)
+    } else {
+        None
+    };
+    if let Some(book_move) = book_move {
+        println!(
+            "info string book move {} weight {} games {}",
+            book_move.mv,
+            book_move.weight,
+            book_move.games()
+        );
+        return book_move.mv;
+    }
+
+    let mv = tree.uct(pos, limits);
+    if let Some(score) = tree.exact_score() {
+        println!("info string endgame solved, final stones {:+}", score);
+    }
+    mv
+}
+
+// position fen <fen> [moves ...]
+// position startpos [name] [moves ...]
+// The position is left alone if anything fails to parse
+fn parse_position(token: &[&str], start_pos: &str) -> Option<Position> {
+    let moves_idx = token
+        .iter()
+        .position(|t| *t == "moves")
+        .unwrap_or(token.len());
+
+    let fen = match token.get(1) {
+        Some(&"fen") => token[2..moves_idx].join(" "),
+        Some(&"startpos") => {
+            let name = if moves_idx > 2 { token[2] } else { start_pos };
+            match startpos::find(name) {
+                Some(start) => start.fen.to_string(),
+                None => {
+                    println!("Unknown start position {}", name);
+                    return None;
+                }
+            }
+        }
+        _ => return None,
+    };
+
+    println!("{}", fen);
+
+    let Ok(mut pos) = Position::from_fen(&fen) else {
+        println!("Failed to read fen");
+        return None;
+    };
+
+    for text in token.iter().skip(moves_idx + 1) {
+        match pos.parse_move(text) {
+            Ok(mv) => pos.make_move(mv),
+            Err(_) => {
+                println!("Illegal move {}", text);
+                return None;
+            }
+        }
+    }
+
+    Some(pos)
+}
+
+fn load_book(path: &str) -> Book {
+    if path.is_empty() {
+        return Book::default();
+    }
+
+    match Book::load(path) {
+        Ok(book) => {
+            println!(
+                "info string loaded book {} with {} positions",
+                path,
+                book.len()
+            );
+            book
+        }
+        Err(err) => {
+            println!("info string failed to load book {}: {:?}", path, err);
+            Book::default()
+        }
+    }
+}
+
+fn load_table(path: &str) -> Option<SolvedTable> {
+    if path.is_empty() {
+        return None;
+    }
+
+    match SolvedTable::load(path) {
+        Ok(table) => {
+            println!(
+                "info string loaded table {} with {} positions",
+                path,
+                table.len()
+            );
+            Some(table)
+        }
+        Err(err) => {
+            println!("info string failed to load table {}: {}", path, err);
+            None
+        }
+    }
+}
@@ -1 +1,3 @@
+mod go;
 pub mod handler;
+mod options;
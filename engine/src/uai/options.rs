@@ -0,0 +1,432 @@
+use crate::ataxx::startpos::{self, CATALOGUE};
+use crate::engine::params::{
+    Evaluation, FinalMove, ParallelMode, Prior, RolloutPolicy, RootSelection, SearchParams,
+    Selection,
+};
+
+#[derive(Debug, PartialEq)]
+pub enum OptionError {
+    Malformed,
+    UnknownName,
+    InvalidValue,
+}
+
+pub fn print_options() {
+    println!("option name Threads type spin default 1 min 1 max 256");
+    println!("option name ParallelMode type combo default Tree var Tree var Root");
+    println!("option name VirtualLoss type spin default 1 min 0 max 100");
+    println!("option name RolloutPolicy type combo default Random var Random var CaptureGreedy var EpsilonGreedy var Softmax");
+    println!("option name Playouts type spin default 10 min 1 max 1000");
+    // Percent
+    println!("option name RolloutEpsilon type spin default 10 min 0 max 100");
+    // Hundredths of a stone
+    println!("option name SoftmaxTemp type spin default 100 min 1 max 10000");
+    println!("option name TruncatedRollouts type check default false");
+    println!("option name RolloutDepth type spin default 20 min 0 max 200");
+    println!("option name Evaluation type combo default Stones var Stones var Static");
+    // Hundredths of a stone
+    println!("option name EvalScale type spin default 500 min 1 max 10000");
+    println!("option name RAVE type check default false");
+    println!("option name RAVEEquivalence type spin default 1000 min 1 max 100000");
+    println!("option name Selection type combo default UCB1 var UCB1 var PUCT");
+    println!("option name Prior type combo default Heuristic var Uniform var Heuristic");
+    // Hundredths
+    println!("option name CPUCT type spin default 150 min 1 max 10000");
+    // Percent
+    println!("option name FPU type spin default 50 min 0 max 100");
+    println!("option name OrderedExpansion type check default true");
+    println!("option name ProgressiveWidening type check default false");
+    // Hundredths
+    println!("option name WideningC type spin default 200 min 1 max 10000");
+    println!("option name WideningAlpha type spin default 50 min 1 max 100");
+    println!("option name RootSelection type combo default UCT var UCT var Gumbel");
+    println!("option name GumbelK type spin default 16 min 1 max 256");
+    println!("option name GumbelSimulations type spin default 200 min 1 max 1000000");
+    println!("option name GraphSearch type check default false");
+    println!("option name SymmetryHash type check default false");
+    println!("option name FinalMove type combo default MostVisits var MostVisits var MaxValue var Secure var MaxValueVisits");
+    // Hundredths
+    println!("option name SecureC type spin default 100 min 0 max 10000");
+    // Needs PUCT selection or a Gumbel root, the search ignores it otherwise
+    println!("option name RootNoise type check default false");
+    // Hundredths
+    println!("option name DirichletAlpha type spin default 30 min 1 max 1000");
+    // Percent
+    println!("option name DirichletEpsilon type spin default 25 min 0 max 100");
+    // Hundredths
+    println!("option name Temperature type spin default 100 min 1 max 1000");
+    println!("option name TemperaturePlies type spin default 0 min 0 max 100");
+    println!("option name Seed type spin default 0 min 0 max 2147483647");
+    // Percent
+    println!("option name MarginWeight type spin default 0 min 0 max 100");
+    println!("option name MateCheck type check default false");
+    println!("option name MateCheckMoves type spin default 3 min 1 max 50");
+    // MB
+    println!("option name PNSMemory type spin default 16 min 1 max 4096");
+    println!("option name EndgameEmpties type spin default 8 min 0 max 49");
+    println!("option name EndgameNodes type spin default 2000000 min 1 max 1000000000");
+    println!("option name BookFile type string default <empty>");
+    println!("option name TableFile type string default <empty>");
+    println!("option name MultiPV type spin default 1 min 1 max 256");
+    println!("option name ShowWDL type check default false");
+
+    let names: Vec<String> = CATALOGUE
+        .iter()
+        .map(|s| format!("var {}", s.name))
+        .collect();
+    println!(
+        "option name StartPos type combo default {} {}",
+        startpos::STANDARD,
+        names.join(" ")
+    );
+}
+
+// setoption name <name> value <value>
+pub fn set_option(params: &mut SearchParams, token: &[&str]) -> Result<(), OptionError> {
+    let value_idx = token
+        .iter()
+        .position(|t| *t == "value")
+        .ok_or(OptionError::Malformed)?;
+
+    if token.get(1) != Some(&"name") || value_idx < 3 {
+        return Err(OptionError::Malformed);
+    }
+
+    let name = token[2..value_idx].join(" ");
+    let value = token[value_idx + 1..].join(" ");
+
+    match name.to_lowercase().as_str() {
+        "threads" => params.threads = parse_spin(&value, 1, 256)?,
+        "parallelmode" => {
+            params.parallel = match value.to_lowercase().as_str() {
+                "tree" => ParallelMode::Tree,
+                "root" => ParallelMode::Root,
+                _ => return Err(OptionError::InvalidValue),
+            }
+        }
+        "virtualloss" => params.virtual_loss = parse_spin(&value, 0, 100)?,
+        "rolloutpolicy" => {
+            params.rollout = RolloutPolicy::from_name(&value).ok_or(OptionError::InvalidValue)?
+        }
+        "playouts" => params.playouts = parse_spin(&value, 1, 1000)?,
+        "rolloutepsilon" => params.epsilon = parse_spin(&value, 0, 100)? as f32 / 100.0,
+        "softmaxtemp" => params.softmax_temp = parse_spin(&value, 1, 10000)? as f32 / 100.0,
+        "truncatedrollouts" => params.truncated = parse_check(&value)?,
+        "rolloutdepth" => params.rollout_depth = parse_spin(&value, 0, 200)?,
+        "evaluation" => {
+            params.evaluation = match value.to_lowercase().as_str() {
+                "stones" => Evaluation::Stones,
+                "static" => Evaluation::Static,
+                _ => return Err(OptionError::InvalidValue),
+            }
+        }
+        "evalscale" => params.eval_scale = parse_spin(&value, 1, 10000)? as f32 / 100.0,
+        "rave" => params.rave = parse_check(&value)?,
+        "raveequivalence" => params.rave_equivalence = parse_spin(&value, 1, 100000)? as f32,
+        "selection" => {
+            params.selection = match value.to_lowercase().as_str() {
+                "ucb1" => Selection::Ucb1,
+                "puct" => Selection::Puct,
+                _ => return Err(OptionError::InvalidValue),
+            }
+        }
+        "prior" => {
+            params.prior = match value.to_lowercase().as_str() {
+                "uniform" => Prior::Uniform,
+                "heuristic" => Prior::Heuristic,
+                _ => return Err(OptionError::InvalidValue),
+            }
+        }
+        "cpuct" => params.cpuct = parse_spin(&value, 1, 10000)? as f32 / 100.0,
+        "fpu" => params.fpu = parse_spin(&value, 0, 100)? as f32 / 100.0,
+        "orderedexpansion" => params.ordered_expansion = parse_check(&value)?,
+        "progressivewidening" => params.widening = parse_check(&value)?,
+        "wideningc" => params.widening_c = parse_spin(&value, 1, 10000)? as f32 / 100.0,
+        "wideningalpha" => params.widening_alpha = parse_spin(&value, 1, 100)? as f32 / 100.0,
+        "rootselection" => {
+            params.root_selection = match value.to_lowercase().as_str() {
+                "uct" => RootSelection::Uct,
+                "gumbel" => RootSelection::Gumbel,
+                _ => return Err(OptionError::InvalidValue),
+            }
+        }
+        "gumbelk" => params.gumbel_k = parse_spin(&value, 1, 256)?,
+        "gumbelsimulations" => params.gumbel_simulations = parse_spin(&value, 1, 1000000)?,
+        "graphsearch" => params.graph = parse_check(&value)?,
+        "symmetryhash" => params.symmetry_hash = parse_check(&value)?,
+        "finalmove" => {
+            params.final_move = match value.to_lowercase().as_str() {
+                "mostvisits" => FinalMove::MostVisits,
+                "maxvalue" => FinalMove::MaxValue,
+                "secure" => FinalMove::Secure,
+                "maxvaluevisits" => FinalMove::MaxValueVisits,
+                _ => return Err(OptionError::InvalidValue),
+            }
+        }
+        "securec" => params.secure_c = parse_spin(&value, 0, 10000)? as f32 / 100.0,
+        "rootnoise" => params.root_noise = parse_check(&value)?,
+        "dirichletalpha" => params.dirichlet_alpha = parse_spin(&value, 1, 1000)? as f32 / 100.0,
+        "dirichletepsilon" => params.dirichlet_epsilon = parse_spin(&value, 0, 100)? as f32 / 100.0,
+        "temperature" => params.temperature = parse_spin(&value, 1, 1000)? as f32 / 100.0,
+        "temperatureplies" => params.temperature_plies = parse_spin(&value, 0, 100)?,
+        "seed" => params.seed = parse_spin(&value, 0, 2147483647)?,
+        "marginweight" => params.margin_weight = parse_spin(&value, 0, 100)? as f32 / 100.0,
+        "matecheck" => params.mate_check = parse_check(&value)?,
+        "matecheckmoves" => params.mate_check_moves = parse_spin(&value, 1, 50)?,
+        "pnsmemory" => params.pns_memory = parse_spin(&value, 1, 4096)?,
+        "endgameempties" => params.endgame_empties = parse_spin(&value, 0, 49)?,
+        "endgamenodes" => params.endgame_nodes = parse_spin(&value, 1, 1000000000)?,
+        "bookfile" => {
+            params.book_file = match value.as_str() {
+                "<empty>" => String::new(),
+                path => path.to_string(),
+            }
+        }
+        "tablefile" => {
+            params.table_file = match value.as_str() {
+                "<empty>" => String::new(),
+                path => path.to_string(),
+            }
+        }
+        "multipv" => params.multipv = parse_spin(&value, 1, 256)?,
+        "showwdl" => params.show_wdl = parse_check(&value)?,
+        "startpos" => {
+            params.start_pos = startpos::find(&value)
+                .ok_or(OptionError::InvalidValue)?
+                .name
+        }
+        _ => return Err(OptionError::UnknownName),
+    }
+
+    Ok(())
+}
+
+fn parse_check(value: &str) -> Result<bool, OptionError> {
+    match value.to_lowercase().as_str() {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(OptionError::InvalidValue),
+    }
+}
+
+fn parse_spin<T: std::str::FromStr + PartialOrd>(
+    value: &str,
+    min: T,
+    max: T,
+) -> Result<T, OptionError> {
+    match value.parse::<T>() {
+        Ok(v) if min <= v && v <= max => Ok(v),
+        _ => Err(OptionError::InvalidValue),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(params: &mut SearchParams, cmd: &str) -> Result<(), OptionError> {
+        let token: Vec<&str> = cmd.split_whitespace().collect();
+        set_option(params, &token)
+    }
+
+    #[test]
+    fn set_options() {
+        let mut params = SearchParams::default();
+        assert_eq!(set(&mut params, "setoption name Threads value 8"), Ok(()));
+        assert_eq!(params.threads, 8);
+
+        assert_eq!(
+            set(&mut params, "setoption name ParallelMode value Root"),
+            Ok(())
+        );
+        assert_eq!(params.parallel, ParallelMode::Root);
+
+        assert_eq!(
+            set(&mut params, "setoption name virtualloss value 3"),
+            Ok(())
+        );
+        assert_eq!(params.virtual_loss, 3);
+
+        assert_eq!(
+            set(&mut params, "setoption name RolloutPolicy value softmax"),
+            Ok(())
+        );
+        assert_eq!(params.rollout, RolloutPolicy::Softmax);
+
+        assert_eq!(set(&mut params, "setoption name Playouts value 4"), Ok(()));
+        assert_eq!(params.playouts, 4);
+
+        assert_eq!(
+            set(&mut params, "setoption name RolloutEpsilon value 25"),
+            Ok(())
+        );
+        assert_eq!(params.epsilon, 0.25);
+
+        assert_eq!(
+            set(&mut params, "setoption name TruncatedRollouts value true"),
+            Ok(())
+        );
+        assert!(params.truncated);
+
+        assert_eq!(
+            set(&mut params, "setoption name RolloutDepth value 0"),
+            Ok(())
+        );
+        assert_eq!(params.rollout_depth, 0);
+
+        assert_eq!(set(&mut params, "setoption name RAVE value true"), Ok(()));
+        assert!(params.rave);
+
+        assert_eq!(
+            set(&mut params, "setoption name Selection value PUCT"),
+            Ok(())
+        );
+        assert_eq!(params.selection, Selection::Puct);
+
+        assert_eq!(set(&mut params, "setoption name FPU value 20"), Ok(()));
+        assert_eq!(params.fpu, 0.2);
+
+        assert_eq!(
+            set(&mut params, "setoption name ProgressiveWidening value true"),
+            Ok(())
+        );
+        assert!(params.widening);
+
+        assert_eq!(
+            set(&mut params, "setoption name WideningAlpha value 25"),
+            Ok(())
+        );
+        assert_eq!(params.widening_alpha, 0.25);
+
+        assert_eq!(
+            set(&mut params, "setoption name RootSelection value Gumbel"),
+            Ok(())
+        );
+        assert_eq!(params.root_selection, RootSelection::Gumbel);
+
+        assert_eq!(
+            set(&mut params, "setoption name GraphSearch value true"),
+            Ok(())
+        );
+        assert!(params.graph);
+
+        assert_eq!(
+            set(&mut params, "setoption name FinalMove value Secure"),
+            Ok(())
+        );
+        assert_eq!(params.final_move, FinalMove::Secure);
+
+        assert_eq!(
+            set(&mut params, "setoption name DirichletAlpha value 3"),
+            Ok(())
+        );
+        assert_eq!(params.dirichlet_alpha, 0.03);
+
+        assert_eq!(
+            set(&mut params, "setoption name RootNoise value true"),
+            Ok(())
+        );
+        assert!(params.root_noise);
+
+        assert_eq!(set(&mut params, "setoption name Seed value 42"), Ok(()));
+        assert_eq!(params.seed, 42);
+
+        assert_eq!(
+            set(&mut params, "setoption name MarginWeight value 30"),
+            Ok(())
+        );
+        assert_eq!(params.margin_weight, 0.3);
+
+        assert_eq!(
+            set(&mut params, "setoption name PNSMemory value 64"),
+            Ok(())
+        );
+        assert_eq!(params.pns_memory, 64);
+
+        assert_eq!(
+            set(&mut params, "setoption name EndgameEmpties value 0"),
+            Ok(())
+        );
+        assert_eq!(params.endgame_empties, 0);
+
+        assert_eq!(
+            set(
+                &mut params,
+                "setoption name BookFile value books/my book.txt"
+            ),
+            Ok(())
+        );
+        assert_eq!(params.book_file, "books/my book.txt");
+        assert_eq!(
+            set(&mut params, "setoption name BookFile value <empty>"),
+            Ok(())
+        );
+        assert_eq!(params.book_file, "");
+
+        assert_eq!(
+            set(&mut params, "setoption name TableFile value 4x4.bin"),
+            Ok(())
+        );
+        assert_eq!(params.table_file, "4x4.bin");
+
+        assert_eq!(
+            set(&mut params, "setoption name StartPos value Cross"),
+            Ok(())
+        );
+        assert_eq!(params.start_pos, "cross");
+
+        assert_eq!(set(&mut params, "setoption name MultiPV value 4"), Ok(()));
+        assert_eq!(params.multipv, 4);
+
+        assert_eq!(
+            set(&mut params, "setoption name ShowWDL value true"),
+            Ok(())
+        );
+        assert!(params.show_wdl);
+    }
+
+    #[test]
+    fn option_errors() {
+        let mut params = SearchParams::default();
+        assert_eq!(
+            set(&mut params, "setoption name Threads value 0"),
+            Err(OptionError::InvalidValue)
+        );
+        assert_eq!(
+            set(&mut params, "setoption name Threads"),
+            Err(OptionError::Malformed)
+        );
+        assert_eq!(
+            set(&mut params, "setoption name Hash value 16"),
+            Err(OptionError::UnknownName)
+        );
+        assert_eq!(params.threads, 1);
+
+        assert_eq!(
+            set(&mut params, "setoption name StartPos value hexagon"),
+            Err(OptionError::InvalidValue)
+        );
+    }
+
+    #[test]
+    fn root_noise_in_any_order() {
+        // Alphabetical order sends RootNoise before Selection
+        let mut params = SearchParams::default();
+        assert_eq!(
+            set(&mut params, "setoption name RootNoise value true"),
+            Ok(())
+        );
+        assert_eq!(
+            set(&mut params, "setoption name Selection value PUCT"),
+            Ok(())
+        );
+        assert!(params.root_noise && params.root_priors());
+
+        assert_eq!(
+            set(&mut params, "setoption name Selection value UCB1"),
+            Ok(())
+        );
+        assert_eq!(params.selection, Selection::Ucb1);
+        assert!(params.root_noise);
+    }
+}